@@ -0,0 +1,41 @@
+#![no_main]
+
+//! Feeds a sequence of datagrams from several senders through the video decoder, the way
+//! `handle_video` does. The input is split into datagrams by a sender byte and a two byte
+//! length in front of each.
+
+use lib::network::{SourceFilter, VideoDecoder};
+use lib::{Statistics, TimingDetector};
+use libfuzzer_sys::fuzz_target;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fuzz_target!(|data: &[u8]| {
+    let filter = SourceFilter::new(Vec::new(), true, None, Duration::from_millis(5));
+    let mut decoder = VideoDecoder::new(
+        Arc::new(Mutex::new(filter)),
+        Arc::new(Mutex::new(TimingDetector::new())),
+        Arc::new(Mutex::new(Statistics::new())),
+    );
+
+    let start = Instant::now();
+    let mut rest = data;
+    let mut tick = 0;
+    while let [sender, high, low, tail @ ..] = rest {
+        let len = usize::from(u16::from_be_bytes([*high, *low])).min(tail.len());
+        let (datagram, tail) = tail.split_at(len);
+        rest = tail;
+
+        let src = SocketAddr::from((Ipv4Addr::new(10, 0, 0, sender % 4), 11_000));
+        let now = start + Duration::from_millis(tick);
+        tick += u64::from(*sender / 4);
+        if *sender == 0xFF {
+            decoder.signal_lost();
+        } else if let Some(frame) = decoder.datagram(now, src, datagram) {
+            assert!(frame.width > 0);
+            assert_eq!(frame.pixels.len(), frame.stride() * frame.height);
+            assert_eq!(frame.concealed.len(), frame.height);
+        }
+    }
+});
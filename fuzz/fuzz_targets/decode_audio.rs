@@ -0,0 +1,10 @@
+#![no_main]
+
+use lib::codec::decode_audio;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(stream) = decode_audio(data) {
+        assert_eq!(stream.encode(), data);
+    }
+});
@@ -0,0 +1,16 @@
+#![no_main]
+
+use lib::codec::{VideoHeader, decode_video};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(packet) = decode_video(data) {
+        // A valid packet always holds exactly the pixels its header announces
+        assert_eq!(packet.data.len(), packet.header().payload_size());
+        assert_eq!(
+            &packet.encode()[..VideoHeader::SIZE - 2],
+            &data[..VideoHeader::SIZE - 2]
+        );
+        assert!(decode_video(&packet.encode_rle()).is_ok());
+    }
+});
@@ -0,0 +1,26 @@
+#![no_main]
+
+//! Draws frames of any claimed geometry, whether or not it matches the pixel data, into a
+//! window buffer of any size.
+
+use lib::video::draw_frame;
+use lib::{COLORS, Frame};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let [w0, w1, h0, h1, bits, buffer_lines, rest @ ..] = data else {
+        return;
+    };
+    let width = usize::from(u16::from_le_bytes([*w0, *w1])) % 4096;
+    let height = usize::from(u16::from_le_bytes([*h0, *h1])) % 2048;
+    let frame = Frame {
+        width,
+        height,
+        bits: *bits,
+        pixels: rest.to_vec(),
+        concealed: rest.iter().map(|b| b & 1 != 0).collect(),
+    };
+
+    let mut buffer = vec![0u32; width * usize::from(*buffer_lines)];
+    draw_frame(&mut buffer, &frame, &COLORS, true);
+});
@@ -1,14 +1,26 @@
 use clap::Parser;
-use std::{net::Ipv4Addr, str::FromStr};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::audio::{AudioOutput, DeviceSelector, WavFormat};
+use crate::network::{Interface, NetworkConfig};
+use crate::replay::Speed;
+use crate::timing::TimingProfile;
 
 /// C64 Ultimate Stream viewer
 ///
 /// This viewer receives and displays real-time video and audio streams multicast over the
 /// network from a Commodore 64 Ultimate cartridge. It decodes the C64's 4-bit indexed color
-/// video frames (384x272 pixels) and plays back the accompanying stereo audio at 48kHz,
-/// allowing you to watch and hear the C64's output on your computer screen.
+/// video frames (384x272 pixels) and plays back the accompanying stereo audio, resampled from
+/// the rate of the PAL or NTSC machine to the rate negotiated with the audio device, allowing
+/// you to watch and hear the C64's output on your computer screen.
 #[derive(Debug, Parser)]
 #[command(version)]
+#[allow(clippy::struct_excessive_bools)] // Command-line switches
 pub struct Args {
     /// Window dimension (e.g. 320x200, 640x480)
     #[arg(short, long, value_parser = parse_dimensions, default_value = "384x272")]
@@ -16,6 +28,23 @@ pub struct Args {
     /// Mute audio
     #[arg(short, long, default_value_t = false)]
     pub mute: bool,
+    /// Audio latency to keep in milliseconds
+    #[arg(
+        long,
+        value_name = "MS",
+        value_parser = clap::value_parser!(u64).range(10..=1000),
+        default_value_t = 100
+    )]
+    pub latency: u64,
+    /// Audio host to play on (e.g. ALSA, JACK)
+    #[arg(long, value_name = "NAME")]
+    pub audio_host: Option<String>,
+    /// Output device by index or (part of) its name as shown by --list-audio-devices
+    #[arg(long, value_name = "NAME|INDEX")]
+    pub audio_device: Option<DeviceSelector>,
+    /// List the audio hosts and their output devices and exit
+    #[arg(long, default_value_t = false)]
+    pub list_audio_devices: bool,
     /// Alternate RGB palette
     #[arg(short, long, value_parser = parse_palette, value_delimiter = ',')]
     pub palette: Vec<u32>,
@@ -31,6 +60,83 @@ pub struct Args {
     /// Use alternate port number for audio
     #[arg(long, default_value_t = 11_001)]
     pub audio_port: u16,
+    /// Tint video lines that were lost and filled from the previous frame
+    #[arg(long, default_value_t = false)]
+    pub show_loss: bool,
+    /// Show the on-screen display with stream statistics
+    #[arg(long, default_value_t = false)]
+    pub osd: bool,
+    /// Seconds without packets before showing "no signal" and rejoining the multicast group
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..), default_value_t = 2)]
+    pub signal_timeout: u64,
+    /// Network interface to receive on, by address or name (e.g. 192.168.1.10, eth0)
+    #[arg(short, long)]
+    pub interface: Option<Interface>,
+    /// Kernel receive buffer size in bytes, may be limited by the operating system
+    #[arg(long, default_value_t = 2 * 1024 * 1024)]
+    pub recv_buffer: usize,
+    /// Only accept packets from these sender addresses (e.g. 192.168.1.64,192.168.1.65)
+    #[arg(long, value_delimiter = ',')]
+    pub allow_source: Vec<IpAddr>,
+    /// Split the streams by sender address and show only one of them
+    #[arg(long, default_value_t = false)]
+    pub demux: bool,
+    /// Sender address to show, implies --demux (default: the first sender)
+    #[arg(long)]
+    pub source: Option<IpAddr>,
+    /// Record the received datagrams to a capture file from the start
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["replay", "synthetic"])]
+    pub record: Option<PathBuf>,
+    /// Record the received audio to a WAV file, also when muted
+    #[arg(long, value_name = "FILE")]
+    pub record_wav: Option<PathBuf>,
+    /// Sample format of the WAV file (pcm16 or float)
+    #[arg(long, value_name = "FORMAT", default_value = "pcm16")]
+    pub wav_format: WavFormat,
+    /// Replay a pcap, pcapng or capture file instead of receiving from the network
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Replay speed, e.g. 2 for twice as fast, 0.5 for half speed or max
+    #[arg(long, default_value = "1")]
+    pub speed: Speed,
+    /// Show a generated test pattern with a test tone instead of receiving (pal or ntsc)
+    #[arg(
+        long,
+        value_name = "STANDARD",
+        value_parser = parse_profile,
+        num_args = 0..=1,
+        default_missing_value = "pal",
+        conflicts_with = "replay"
+    )]
+    pub synthetic: Option<TimingProfile>,
+}
+
+impl Args {
+    /// Audio host and device from the command line
+    #[must_use]
+    pub fn audio_output(&self) -> AudioOutput {
+        AudioOutput {
+            host: self.audio_host.clone(),
+            device: self.audio_device.clone(),
+        }
+    }
+
+    /// Network settings from the command line
+    #[must_use]
+    pub fn network_config(&self) -> NetworkConfig {
+        NetworkConfig {
+            video_maddr: self.video_maddr,
+            audio_maddr: self.audio_maddr,
+            video_port: self.video_port,
+            audio_port: self.audio_port,
+            timeout: Duration::from_secs(self.signal_timeout),
+            interface: self.interface.clone(),
+            recv_buffer: self.recv_buffer,
+            allowed_sources: self.allow_source.clone(),
+            demux: self.demux,
+            source: self.source,
+        }
+    }
 }
 
 fn parse_dimensions(s: &str) -> Result<(usize, usize), String> {
@@ -73,3 +179,13 @@ fn parse_maddr(addr: &str) -> Result<Ipv4Addr, String> {
     }
     Ok(addr)
 }
+
+fn parse_profile(s: &str) -> Result<TimingProfile, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "pal" => Ok(TimingProfile::Pal),
+        "ntsc" => Ok(TimingProfile::Ntsc),
+        _ => Err(format!(
+            "Unknown video standard '{s}', expected pal or ntsc"
+        )),
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+
+use crate::codec::SAMPLES_PER_PACKET;
+
+pub const REORDER_WINDOW: u16 = 2; // A missing packet is lost once a packet this far past it arrives
+pub const MAX_CONCEALED: u16 = 25; // Longest gap in packets that is filled, about 100 ms
+pub const MAX_LATE: u16 = 2 * REORDER_WINDOW; // Packets further behind are from a restarted sender
+
+type Packet = [[f32; 2]; SAMPLES_PER_PACKET];
+
+/// Puts the audio packets of one sender back in order and conceals the ones that are lost
+///
+/// Packets that arrive early wait for the missing ones until the reorder window is exceeded.
+/// A gap is as long as the packets missing from the sequence. It is filled by playing the
+/// packet before it backwards while fading out, crossfaded with the packet after it played
+/// backwards while fading in, so the waveform has no jumps at either end. Longer gaps are
+/// silent between the fades. A jump forward past `MAX_CONCEALED` packets or backward past
+/// `MAX_LATE` packets is treated as a restart of the sender and bridged as a single lost
+/// packet.
+#[derive(Debug, Default)]
+pub struct Concealer {
+    expected: Option<u16>,             // Next sequence number to play
+    pending: VecDeque<Option<Packet>>, // Packets from `expected` on that arrived early
+    previous: Option<Packet>,          // Last packet played
+    missing: u16,                      // Packets lost since `previous`
+    lost: u64,                         // Packets concealed
+    late: u64,                         // Packets dropped as they arrived after they were played
+    restarts: u64,                     // Jumps in the sequence treated as a restart
+}
+
+impl Concealer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the packet `seq` with stereo `samples` and returns the interleaved samples
+    /// that can be played now
+    pub fn push(&mut self, seq: u16, samples: &[[i16; 2]; SAMPLES_PER_PACKET]) -> Vec<f32> {
+        let expected = *self.expected.get_or_insert(seq);
+        let mut ahead = seq.wrapping_sub(expected);
+        let behind = expected.wrapping_sub(seq);
+        if ahead >= 0x8000 && behind <= MAX_LATE {
+            self.late += 1;
+            return Vec::new();
+        }
+
+        let mut output = Vec::new();
+        if ahead >= 0x8000 || ahead > MAX_CONCEALED + REORDER_WINDOW {
+            self.restart(seq, &mut output);
+            ahead = 0;
+        }
+
+        let index = usize::from(ahead);
+        if self.pending.len() <= index {
+            self.pending.resize(index + 1, None);
+        }
+        if self.pending[index].is_some() {
+            // Duplicate
+            return output;
+        }
+        self.pending[index] = Some(samples.map(|pair| pair.map(|s| f32::from(s) / 32768.)));
+
+        self.play_ready(&mut output);
+        while self.pending.len() > usize::from(REORDER_WINDOW) {
+            self.skip();
+            self.play_ready(&mut output);
+        }
+        output
+    }
+
+    /// Packets concealed so far
+    #[must_use]
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Packets dropped so far because they arrived after their place was played or concealed
+    #[must_use]
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+
+    /// Jumps in the sequence so far that were treated as a restart of the sender
+    #[must_use]
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    // Continues with `seq` after a jump in the sequence
+    fn restart(&mut self, seq: u16, output: &mut Vec<f32>) {
+        // Play what arrived, the rest of the old stream is not coming anymore
+        while !self.pending.is_empty() {
+            self.skip();
+            self.play_ready(output);
+        }
+        self.expected = Some(seq);
+        self.missing = u16::from(self.previous.is_some());
+        self.restarts += 1;
+    }
+
+    // Gives up on the expected packet
+    fn skip(&mut self) {
+        self.pending.pop_front();
+        self.advance();
+        self.missing = self.missing.saturating_add(1);
+        self.lost += 1;
+    }
+
+    // Plays the packets that are next in line
+    fn play_ready(&mut self, output: &mut Vec<f32>) {
+        while let Some(Some(packet)) = self.pending.front() {
+            let packet = *packet;
+            self.pending.pop_front();
+            self.advance();
+            if self.missing > 0 {
+                let frames = usize::from(self.missing.min(MAX_CONCEALED)) * SAMPLES_PER_PACKET;
+                output.extend(conceal(self.previous.as_ref(), &packet, frames).as_flattened());
+                self.missing = 0;
+            }
+            output.extend(packet.as_flattened());
+            self.previous = Some(packet);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.expected = self.expected.map(|seq| seq.wrapping_add(1));
+    }
+}
+
+// Fills a gap of `frames` stereo frames between `previous` and `next`
+fn conceal(previous: Option<&Packet>, next: &Packet, frames: usize) -> Vec<[f32; 2]> {
+    let fade = frames.min(SAMPLES_PER_PACKET);
+    #[allow(clippy::cast_precision_loss)]
+    let gain = |i: usize| 1. - (i + 1) as f32 / (fade + 1) as f32;
+    let mut output = vec![[0.; 2]; frames];
+    if let Some(previous) = previous {
+        for (i, frame) in output.iter_mut().take(fade).enumerate() {
+            let mirrored = previous[SAMPLES_PER_PACKET - 1 - i];
+            frame[0] += mirrored[0] * gain(i);
+            frame[1] += mirrored[1] * gain(i);
+        }
+    }
+    for (i, frame) in output.iter_mut().rev().take(fade).enumerate() {
+        let mirrored = next[i];
+        frame[0] += mirrored[0] * gain(i);
+        frame[1] += mirrored[1] * gain(i);
+    }
+    output
+}
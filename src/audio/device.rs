@@ -0,0 +1,139 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host};
+use std::fmt;
+use std::str::FromStr;
+use tracing::debug;
+
+/// Output device to play the audio on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    Index(usize), // Position in the list of output devices of the host
+    Name(String), // Name of the device, or a part of it
+}
+
+impl FromStr for DeviceSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Audio device must not be empty".to_string());
+        }
+        Ok(s.parse()
+            .map_or_else(|_| Self::Name(s.to_string()), Self::Index))
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "{index}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl DeviceSelector {
+    /// Position of the selected device in `names`, a name that matches exactly, ignoring
+    /// case, wins over the first name that contains it
+    #[must_use]
+    pub fn find(&self, names: &[String]) -> Option<usize> {
+        match self {
+            Self::Index(index) => (*index < names.len()).then_some(*index),
+            Self::Name(name) => {
+                let name = name.to_lowercase();
+                let names: Vec<String> = names.iter().map(|n| n.to_lowercase()).collect();
+                names
+                    .iter()
+                    .position(|n| *n == name)
+                    .or_else(|| names.iter().position(|n| n.contains(&name)))
+            }
+        }
+    }
+}
+
+/// Audio host and output device, the defaults of the system if not given
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioOutput {
+    pub host: Option<String>, // Name of the audio host, e.g. `ALSA` or `JACK`
+    pub device: Option<DeviceSelector>,
+}
+
+/// Output devices of an audio host
+#[derive(Debug, Clone)]
+pub struct HostDevices {
+    pub host: String,
+    pub default: bool,        // Default host of the system
+    pub devices: Vec<String>, // Names in the order `DeviceSelector::Index` counts them
+    pub default_device: Option<usize>,
+}
+
+/// Lists the output devices of all available hosts
+#[must_use]
+pub fn output_devices() -> Vec<HostDevices> {
+    let default_host = cpal::default_host().id();
+    cpal::available_hosts()
+        .into_iter()
+        .filter_map(|id| {
+            let host = cpal::host_from_id(id).ok()?;
+            let default_name = host.default_output_device().map(|d| device_name(&d));
+            let devices: Vec<String> = host
+                .output_devices()
+                .map(|devices| devices.map(|d| device_name(&d)).collect())
+                .unwrap_or_default();
+            Some(HostDevices {
+                host: id.name().to_string(),
+                default: id == default_host,
+                default_device: default_name.and_then(|n| devices.iter().position(|d| *d == n)),
+                devices,
+            })
+        })
+        .collect()
+}
+
+/// Opens the host and output device selected in `output`
+///
+/// # Errors
+/// Returns an error if the host is not available or the device is not found
+pub fn open_device(output: &AudioOutput) -> Result<Device, String> {
+    let host = open_host(output.host.as_deref())?;
+    let device = match &output.device {
+        None => host
+            .default_output_device()
+            .ok_or_else(|| format!("No output device found on {}", host.id().name()))?,
+        Some(selector) => {
+            let mut devices: Vec<Device> = host
+                .output_devices()
+                .map_err(|e| format!("Unable to list output devices: {e}"))?
+                .collect();
+            let names: Vec<String> = devices.iter().map(device_name).collect();
+            let index = selector.find(&names).ok_or_else(|| {
+                format!("Audio device {selector} not found on {}", host.id().name())
+            })?;
+            devices.swap_remove(index)
+        }
+    };
+    debug!(
+        "Using audio device {} on {}",
+        device_name(&device),
+        host.id().name()
+    );
+    Ok(device)
+}
+
+fn open_host(name: Option<&str>) -> Result<Host, String> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("Audio host {name} is not available"))?;
+    cpal::host_from_id(id).map_err(|e| format!("Unable to open audio host {name}: {e}"))
+}
+
+fn device_name(device: &Device) -> String {
+    device
+        .description()
+        .map_or_else(|_| "Unknown device".to_string(), |d| d.name().to_string())
+}
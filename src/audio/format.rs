@@ -0,0 +1,78 @@
+use cpal::{
+    FromSample, SampleFormat, SizedSample, SupportedStreamConfig, SupportedStreamConfigRange,
+};
+
+const DEVICE_RATES: [u32; 2] = [48_000, 44_100]; // Preferred device rates, best first
+
+// Sample formats the stream can be converted to, best first
+const SAMPLE_FORMATS: [SampleFormat; 8] = [
+    SampleFormat::F32,
+    SampleFormat::F64,
+    SampleFormat::I32,
+    SampleFormat::I16,
+    SampleFormat::U32,
+    SampleFormat::U16,
+    SampleFormat::I8,
+    SampleFormat::U8,
+];
+
+/// Configurations to open a device with, best first, from the ranges the device supports
+///
+/// Stereo is preferred over more channels and those over mono, then the sample formats that
+/// keep the most of the float stream, then 48000 Hz and 44100 Hz. Of a range that supports
+/// neither rate the one closest to 48000 Hz is taken. Formats the stream can't be converted to
+/// are left out.
+#[must_use]
+pub fn rank_configs(ranges: &[SupportedStreamConfigRange]) -> Vec<SupportedStreamConfig> {
+    let mut configs: Vec<(usize, usize, usize, SupportedStreamConfig)> = ranges
+        .iter()
+        .filter(|range| range.channels() > 0 && range.min_sample_rate() <= range.max_sample_rate())
+        .filter_map(|range| {
+            let format = SAMPLE_FORMATS
+                .iter()
+                .position(|&f| f == range.sample_format())?;
+            let channels = match range.channels() {
+                2 => 0,
+                1 => usize::MAX,
+                n => usize::from(n),
+            };
+            let rate = DEVICE_RATES
+                .into_iter()
+                .find(|rate| (range.min_sample_rate()..=range.max_sample_rate()).contains(rate))
+                .unwrap_or_else(|| {
+                    DEVICE_RATES[0].clamp(range.min_sample_rate(), range.max_sample_rate())
+                });
+            let rate_rank = DEVICE_RATES
+                .iter()
+                .position(|&r| r == rate)
+                .unwrap_or_else(|| DEVICE_RATES.len() + rate.abs_diff(DEVICE_RATES[0]) as usize);
+            let config = (*range).with_sample_rate(rate);
+            Some((channels, format, rate_rank, config))
+        })
+        .collect();
+    configs.sort_by_key(|&(channels, format, rate, _)| (channels, format, rate));
+    configs
+        .into_iter()
+        .map(|(_, _, _, config)| config)
+        .collect()
+}
+
+/// Converts interleaved `stereo` frames to the sample format and `channels` of the device
+///
+/// Mono is the average of both channels. With more channels the first two get left and right,
+/// which are the front speakers in the common layouts, and the others are silent.
+pub fn write_frames<T>(stereo: &[f32], data: &mut [T], channels: usize)
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let silence = T::from_sample(0.);
+    for (frame, pair) in data.chunks_exact_mut(channels).zip(stereo.chunks_exact(2)) {
+        if let [mono] = frame {
+            *mono = T::from_sample((pair[0] + pair[1]) * 0.5);
+            continue;
+        }
+        frame[0] = T::from_sample(pair[0]);
+        frame[1] = T::from_sample(pair[1]);
+        frame[2..].fill(silence);
+    }
+}
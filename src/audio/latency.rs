@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+const SMOOTHING: f64 = 1.; // Seconds over which the fill is averaged
+const MAX_ADJUST: f64 = 0.002; // Largest deviation of the ratio, about 3.5 cents
+const GAIN: f64 = 0.1; // Ratio change per second of latency off target
+const INTEGRAL_GAIN: f64 = 0.002_5; // Ratio change per second of latency off target per second
+
+/// Samples queued for `latency` of a stream at `sample_rate`, both channels counted
+#[must_use]
+pub fn latency_samples(latency: Duration, sample_rate: f64) -> usize {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let frames = (latency.as_secs_f64() * sample_rate).round() as usize;
+    2 * frames
+}
+
+// Seconds of audio in `samples` of a stream at `sample_rate`, both channels counted
+fn seconds(samples: f64, sample_rate: f64) -> f64 {
+    samples / (2. * sample_rate)
+}
+
+/// Keeps the fill of the audio buffer on target by playing slightly faster or slower
+///
+/// The clocks of the Ultimate and the sound card drift apart, so the buffer would slowly fill
+/// up or run dry at a fixed playback rate. The averaged fill steers a ratio for the stream
+/// rate: the proportional part pulls the fill back within about ten seconds, the integral part
+/// settles on the drift.
+#[derive(Debug, Clone)]
+pub struct LatencyController {
+    target: f64,       // Seconds
+    fill: Option<f64>, // Averaged fill, `None` until the first update
+    integral: f64,
+    ratio: f64,
+}
+
+impl LatencyController {
+    /// Targets `latency` of queued audio
+    #[must_use]
+    pub fn new(latency: Duration) -> Self {
+        Self {
+            target: latency.as_secs_f64(),
+            fill: None,
+            integral: 0.,
+            ratio: 1.,
+        }
+    }
+
+    /// Registers the `fill` of the buffer after playing a stream at `sample_rate` for
+    /// `elapsed` seconds and returns the ratio to multiply the stream rate with
+    pub fn update(&mut self, fill: usize, sample_rate: f64, elapsed: f64) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let fill = fill as f64;
+        let alpha = (elapsed / SMOOTHING).clamp(0., 1.);
+        let average = self
+            .fill
+            .map_or(fill, |average| average + (fill - average) * alpha);
+        self.fill = Some(average);
+
+        let error = seconds(average, sample_rate) - self.target;
+        self.integral =
+            (self.integral + INTEGRAL_GAIN * error * elapsed).clamp(-MAX_ADJUST, MAX_ADJUST);
+        self.ratio = 1. + (GAIN * error + self.integral).clamp(-MAX_ADJUST, MAX_ADJUST);
+        self.ratio
+    }
+
+    /// Forgets the averaged fill after the buffer ran dry, the drift it settled on is kept
+    pub fn restart(&mut self) {
+        self.fill = None;
+    }
+
+    #[must_use]
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Averaged fill in samples
+    #[must_use]
+    pub fn fill(&self) -> Option<f64> {
+        self.fill
+    }
+}
@@ -1,7 +1,20 @@
+mod conceal;
+mod device;
+mod format;
+mod latency;
+mod queue;
+mod recorder;
+mod resampler;
 mod stream;
-use crate::RingBuffer;
 
+pub use conceal::{Concealer, MAX_CONCEALED, MAX_LATE, REORDER_WINDOW};
+pub use device::{AudioOutput, DeviceSelector, HostDevices, open_device, output_devices};
+pub use format::{rank_configs, write_frames};
+pub use latency::{LatencyController, latency_samples};
+pub use queue::SampleQueue;
+pub use recorder::{WavFormat, WavRecorder, WavRecording};
+pub use resampler::Resampler;
 pub use stream::init_audio;
 
-use std::sync::{Arc, Mutex};
-pub type AudioBuffer = Arc<Mutex<RingBuffer<f32>>>;
+use std::sync::Arc;
+pub type AudioBuffer = Arc<SampleQueue>;
@@ -0,0 +1,164 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use crate::TimingProfile;
+
+/// Lock-free queue of samples between one producer thread and one consumer thread
+///
+/// The network thread pushes samples and the audio callback pops them, neither ever blocks
+/// the other. Positions count every sample ever pushed or popped, so the slot of a position
+/// is the position modulo the capacity. Samples are stored as bits in atomics, so using the
+/// queue from more threads can garble samples, but is never undefined behaviour.
+pub struct SampleQueue {
+    slots: Box<[AtomicU32]>,
+    min_fill: usize,
+    head: AtomicUsize,    // Next position to pop, only advanced by the consumer
+    tail: AtomicUsize,    // Next position to push, only advanced by the producer
+    discard: AtomicUsize, // Samples before this position were cleared by the producer
+    starved: AtomicBool,  // Last pop returned silence, only used by the consumer
+    underruns: AtomicU64, // Times playback ran dry
+    overruns: AtomicU64,  // Samples dropped because the queue was full
+    rate: AtomicU64,      // Bits of the stream rate, set by the producer
+}
+
+impl SampleQueue {
+    /// Creates a queue of `capacity` samples, that prebuffers `min_fill` samples before it
+    /// plays, at the start and after it ran dry
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero
+    #[must_use]
+    pub fn new(capacity: usize, min_fill: usize) -> Self {
+        assert!(capacity > 0, "SampleQueue capacity must be positive");
+        Self {
+            slots: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            min_fill,
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            discard: AtomicUsize::new(0),
+            starved: AtomicBool::new(true),
+            underruns: AtomicU64::new(0),
+            overruns: AtomicU64::new(0),
+            rate: AtomicU64::new(TimingProfile::Pal.sample_rate().to_bits()),
+        }
+    }
+
+    /// Queues as many `samples` as fit, returns how many did
+    ///
+    /// Unlike `RingBuffer` the newest samples are dropped when full, as only the consumer may
+    /// move the head. Must only be called from the producer thread.
+    pub fn push_slice(&self, samples: &[f32]) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        // Acquire, so the consumer is done with the slots before they are overwritten. Cleared
+        // slots are free, a pop that started before the clear may play some of the new samples
+        // in their place, which only happens when the samples jump anyway.
+        let head = self
+            .head
+            .load(Ordering::Acquire)
+            .max(self.discard.load(Ordering::Relaxed));
+        let free = self.capacity() - (tail - head);
+        let count = samples.len().min(free);
+        for (position, sample) in (tail..).zip(&samples[..count]) {
+            self.slot(position)
+                .store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.tail.store(tail + count, Ordering::Release);
+
+        let dropped = samples.len() - count;
+        if dropped > 0 {
+            self.overruns.fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+        count
+    }
+
+    /// Fills `out` with the oldest samples, or with silence if fewer are queued or the queue
+    /// is prebuffering. Returns if samples were popped.
+    ///
+    /// A slice is popped whole or not at all, so stereo frames stay aligned. Must only be
+    /// called from the consumer thread.
+    pub fn pop_slice(&self, out: &mut [f32]) -> bool {
+        // Load the discard position first, the tail is never behind it
+        let discard = self.discard.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Relaxed).max(discard);
+
+        let queued = tail - head;
+        let prebuffering = self.starved.load(Ordering::Relaxed) && queued < self.min_fill;
+        if !prebuffering && queued >= out.len() {
+            for (position, sample) in (head..).zip(out.iter_mut()) {
+                *sample = f32::from_bits(self.slot(position).load(Ordering::Relaxed));
+            }
+            self.head.store(head + out.len(), Ordering::Release);
+            self.starved.store(false, Ordering::Relaxed);
+            true
+        } else {
+            out.fill(0.);
+            if !self.starved.swap(true, Ordering::Relaxed) {
+                self.underruns.fetch_add(1, Ordering::Relaxed);
+            }
+            false
+        }
+    }
+
+    /// Discards all queued samples
+    ///
+    /// The consumer skips them on its next pop. Must only be called from the producer thread.
+    pub fn clear(&self) {
+        let tail = self.tail.load(Ordering::Relaxed);
+        self.discard.store(tail, Ordering::Release);
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        let discard = self.discard.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        let head = self.head.load(Ordering::Acquire).max(discard);
+        tail.saturating_sub(head)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Samples the queue prebuffers
+    #[must_use]
+    pub fn min_fill(&self) -> usize {
+        self.min_fill
+    }
+
+    /// Last pop returned silence, the queue prebuffers before it plays again
+    #[must_use]
+    pub fn is_starved(&self) -> bool {
+        self.starved.load(Ordering::Relaxed)
+    }
+
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[must_use]
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    #[must_use]
+    pub fn overruns(&self) -> u64 {
+        self.overruns.load(Ordering::Relaxed)
+    }
+
+    /// Samples per second per channel of the queued stream, PAL until the producer sets it
+    #[must_use]
+    pub fn sample_rate(&self) -> f64 {
+        f64::from_bits(self.rate.load(Ordering::Relaxed))
+    }
+
+    /// Sets the rate of the queued stream, so the consumer can follow it without locking
+    pub fn set_sample_rate(&self, sample_rate: f64) {
+        self.rate.store(sample_rate.to_bits(), Ordering::Relaxed);
+    }
+
+    fn slot(&self, position: usize) -> &AtomicU32 {
+        &self.slots[position % self.slots.len()]
+    }
+}
@@ -0,0 +1,258 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::TimingProfile;
+
+pub type WavRecording = Arc<Mutex<WavRecorder>>;
+
+const MAX_PENDING: Duration = Duration::from_secs(10); // Longest wait for the machine
+
+/// Sample format of a WAV recording
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WavFormat {
+    #[default]
+    Pcm16, // 16-bit signed integers
+    Float, // 32-bit floats, the samples as they are decoded
+}
+
+impl FromStr for WavFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pcm16" | "16" => Ok(Self::Pcm16),
+            "float" | "f32" | "32" => Ok(Self::Float),
+            _ => Err(format!("Invalid WAV format '{s}', expected pcm16 or float")),
+        }
+    }
+}
+
+impl WavFormat {
+    fn spec(self, sample_rate: u32) -> WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            Self::Pcm16 => (16, SampleFormat::Int),
+            Self::Float => (32, SampleFormat::Float),
+        };
+        WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+}
+
+/// Records the decoded stereo stream to WAV files at the rate of the stream while started
+///
+/// The file is only created once the machine is known, so its header has the right rate from
+/// the start. Until then the samples are kept in memory, for at most `MAX_PENDING`, after
+/// which the machine is taken for PAL. When the machine changes while recording, the file is
+/// finished and the recording continues in a new one with a number appended to its name. The
+/// header is brought up to date every second, so the file stays playable if the viewer dies.
+/// Lost packets are concealed before they get here, longer outages are filled with silence as
+/// long as they lasted.
+#[derive(Default)]
+pub struct WavRecorder {
+    path: Option<PathBuf>, // File name given to `start`, if recording
+    format: WavFormat,
+    wav: Option<(TimingProfile, WavWriter<BufWriter<File>>)>,
+    pending: Vec<f32>,     // Samples recorded before the machine is known
+    files: Vec<PathBuf>,   // Files of this recording
+    last: Option<Instant>, // Arrival of the last samples written
+    unflushed: u64,        // Stereo frames written since the header was updated
+}
+
+impl WavRecorder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts recording to `path`, a running recording is stopped first
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be created
+    pub fn start(&mut self, path: &Path, format: WavFormat) -> Result<(), String> {
+        self.stop()?;
+        // Fail now rather than once the machine is known
+        File::create(path)
+            .map_err(|e| format!("Unable to create WAV file {}: {e}", path.display()))?;
+        debug!("Recording audio to {}", path.display());
+        self.path = Some(path.to_path_buf());
+        self.format = format;
+        self.files.clear();
+        self.last = None;
+        Ok(())
+    }
+
+    /// Stops recording and returns the WAV files written, the machine is taken for PAL if it
+    /// is still unknown
+    ///
+    /// # Errors
+    /// Returns an error if a file can't be written or finished
+    pub fn stop(&mut self) -> Result<Vec<PathBuf>, String> {
+        if self.path.is_none() {
+            return Ok(Vec::new());
+        }
+        if self.wav.is_none() {
+            self.open(TimingProfile::Pal)?;
+        }
+        let result = self.finish();
+        self.path = None;
+        self.pending.clear();
+        result?;
+        Ok(std::mem::take(&mut self.files))
+    }
+
+    /// File name given to `start`, if recording
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Records interleaved stereo `samples` of a stream from `profile` that arrived at `now`
+    /// if recording, `profile` is `None` while the machine is unknown
+    ///
+    /// After a `resync`, when the stream restarted or came back after an outage, the time
+    /// since the last samples that `samples` doesn't cover is filled with silence. Recording
+    /// stops when a file can't be written.
+    pub fn write(
+        &mut self,
+        now: Instant,
+        profile: Option<TimingProfile>,
+        samples: &[f32],
+        resync: bool,
+    ) {
+        if self.path.is_none() {
+            return;
+        }
+        let current = self.wav.as_ref().map(|(profile, _)| *profile);
+        let rate = profile
+            .or(current)
+            .unwrap_or(TimingProfile::Pal)
+            .sample_rate();
+        let mut silence = 0;
+        if resync && let Some(last) = self.last {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let elapsed = (now.saturating_duration_since(last).as_secs_f64() * rate) as usize;
+            silence = elapsed.saturating_sub(samples.len() / 2);
+        }
+        self.last = Some(now);
+
+        let error = match (profile, current) {
+            (Some(profile), Some(current)) if profile != current => {
+                debug!("Stream switched to {profile:?}, starting a new WAV file");
+                self.finish().and_then(|()| self.open(profile)).err()
+            }
+            (Some(profile), None) => self.open(profile).err(),
+            (None, None) if self.pending.len() / 2 >= max_pending() => {
+                debug!("Machine still unknown, recording as PAL");
+                self.open(TimingProfile::Pal).err()
+            }
+            _ => None,
+        };
+        let zeros = std::iter::repeat_n(0., 2 * silence);
+        let samples = zeros.chain(samples.iter().copied());
+        let result = match error {
+            Some(e) => Err(e),
+            None if self.wav.is_none() => {
+                self.pending.extend(samples);
+                Ok(())
+            }
+            None => self.append(samples),
+        };
+        if let Err(e) = result {
+            debug!("Stopped recording audio: {e}");
+            self.path = None;
+            self.wav = None;
+            self.pending.clear();
+        }
+    }
+
+    // Creates the next file of the recording with the rate of `profile` and writes the
+    // samples kept until now
+    fn open(&mut self, profile: TimingProfile) -> Result<(), String> {
+        let Some(base) = &self.path else {
+            return Ok(());
+        };
+        let path = numbered(base, self.files.len());
+        let spec = self.format.spec(header_rate(profile.sample_rate()));
+        let writer = WavWriter::create(&path, spec)
+            .map_err(|e| format!("Unable to create WAV file {}: {e}", path.display()))?;
+        debug!("Recording {profile:?} audio to {}", path.display());
+        self.files.push(path);
+        self.wav = Some((profile, writer));
+        self.unflushed = 0;
+        let pending = std::mem::take(&mut self.pending);
+        self.append(pending.into_iter())
+    }
+
+    fn append(&mut self, samples: impl Iterator<Item = f32>) -> Result<(), String> {
+        let Some((profile, writer)) = &mut self.wav else {
+            return Ok(());
+        };
+        let format = self.format;
+        let mut count = 0;
+        for sample in samples {
+            match format {
+                WavFormat::Pcm16 => writer.write_sample(to_i16(sample)),
+                WavFormat::Float => writer.write_sample(sample),
+            }
+            .map_err(|e| format!("Unable to write WAV file: {e}"))?;
+            count += 1;
+        }
+        self.unflushed += count / 2;
+        if self.unflushed >= u64::from(header_rate(profile.sample_rate())) {
+            // Keep the header up to date in case the viewer dies
+            writer
+                .flush()
+                .map_err(|e| format!("Unable to write WAV file: {e}"))?;
+            self.unflushed = 0;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let Some((_, writer)) = self.wav.take() else {
+            return Ok(());
+        };
+        writer
+            .finalize()
+            .map_err(|e| format!("Unable to finish WAV file: {e}"))
+    }
+}
+
+// Stereo frames kept while the machine is unknown
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn max_pending() -> usize {
+    (MAX_PENDING.as_secs_f64() * TimingProfile::Pal.sample_rate()) as usize
+}
+
+// `path` for the first file of a recording, with `-<number>` appended for the next ones
+fn numbered(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}-{}", index + 1);
+    if let Some(extension) = path.extension() {
+        name = format!("{name}.{}", extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_i16(sample: f32) -> i16 {
+    (sample * 32768.).round().clamp(-32768., 32767.) as i16
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn header_rate(rate: f64) -> u32 {
+    rate.round() as u32
+}
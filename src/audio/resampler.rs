@@ -0,0 +1,70 @@
+use crate::AudioBuffer;
+
+/// Converts the interleaved stereo stream in an `AudioBuffer` from the stream rate to the
+/// device rate
+///
+/// Every output frame is interpolated with a cubic Hermite spline through the four stream
+/// frames around it, which keeps the pitch of the stream and is cheap enough for the audio
+/// callback. The rates can change while playing, the phase carries over.
+pub struct Resampler {
+    buffer: AudioBuffer,
+    device_rate: f64,
+    step: f64,             // Stream frames per device frame
+    position: f64,         // Between `frames[1]` and `frames[2]`
+    frames: [[f32; 2]; 4], // Last four stream frames, oldest first
+}
+
+impl Resampler {
+    #[must_use]
+    pub fn new(buffer: AudioBuffer, stream_rate: f64, device_rate: f64) -> Self {
+        Self {
+            buffer,
+            device_rate,
+            step: stream_rate / device_rate,
+            position: 1.,
+            frames: [[0.; 2]; 4],
+        }
+    }
+
+    /// Samples per second per channel of the stream
+    pub fn set_stream_rate(&mut self, stream_rate: f64) {
+        self.step = stream_rate / self.device_rate;
+    }
+
+    #[must_use]
+    pub fn stream_rate(&self) -> f64 {
+        self.step * self.device_rate
+    }
+
+    #[must_use]
+    pub fn device_rate(&self) -> f64 {
+        self.device_rate
+    }
+
+    /// Fills `data` with interleaved stereo frames at the device rate
+    pub fn fill(&mut self, data: &mut [f32]) {
+        for frame in data.chunks_exact_mut(2) {
+            while self.position >= 1. {
+                let mut next = [0.; 2];
+                self.buffer.pop_slice(&mut next);
+                self.frames = [self.frames[1], self.frames[2], self.frames[3], next];
+                self.position -= 1.;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let t = self.position as f32;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let [p0, p1, p2, p3] = self.frames.map(|f| f[channel]);
+                *sample = hermite(p0, p1, p2, p3, t);
+            }
+            self.position += self.step;
+        }
+    }
+}
+
+// Value at `t` between `p1` and `p2`, with the tangents taken from the neighbours
+fn hermite(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (p2 - p0);
+    let c2 = p0 - 2.5 * p1 + 2. * p2 - 0.5 * p3;
+    let c3 = 0.5 * (p3 - p0) + 1.5 * (p1 - p2);
+    ((c3 * t + c2) * t + c1) * t + p1
+}
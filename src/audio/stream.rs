@@ -1,65 +1,155 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Stream, StreamConfig};
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{
+    BuildStreamError, Device, FromSample, SampleFormat, SizedSample, Stream, SupportedBufferSize,
+    SupportedStreamConfig,
+};
 use tracing::{debug, error};
 
-use crate::RingBuffer;
+use super::{AudioOutput, LatencyController, Resampler, open_device, rank_configs, write_frames};
+use crate::AudioBuffer;
 
-pub type AudioBuffer = Arc<Mutex<RingBuffer<f32>>>;
+const MAX_CALLBACK_FRAMES: usize = 8192; // Longer callbacks are played in parts
 
-/// # Panics
-/// Panics if no output device is found or if unable to acquire a `audio_buffer` lock
+/// Plays the audio buffer on the device selected in `output`, keeping about `latency` of audio
+/// queued
+///
+/// The device is opened with the best configuration it supports, the stereo stream is
+/// converted to its sample format and channels.
+///
 /// # Errors
-/// Return an error if stream cannot be played
-pub fn init_audio(audio_buffer: &AudioBuffer) -> Result<Stream, String> {
+/// Return an error if the device is not found, no stream can be created with any of its
+/// configurations or if it cannot be played
+pub fn init_audio(
+    audio_buffer: &AudioBuffer,
+    latency: Duration,
+    output: &AudioOutput,
+) -> Result<Stream, String> {
     debug!("Initializing audio");
-    let host = cpal::default_host();
-    let device = host
-        .default_output_device()
-        .expect("No output device found");
+    let device = open_device(output)?;
+
+    let mut configs = device
+        .supported_output_configs()
+        .map(|ranges| rank_configs(&ranges.collect::<Vec<_>>()))
+        .unwrap_or_default();
+    if configs.is_empty() {
+        configs.extend(device.default_output_config());
+    }
 
-    let audio_callback = {
-        let buffer = audio_buffer.clone();
-        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-            let mut buf = buffer
-                .lock()
-                .expect("Unable to acquire lock on audio_buffer");
-            for sample in data.iter_mut() {
-                *sample = buf.pop();
+    for config in configs {
+        let description = format!(
+            "{} Hz, {} channels, {}",
+            config.sample_rate(),
+            config.channels(),
+            config.sample_format()
+        );
+        match build_stream(&device, &config, audio_buffer, latency) {
+            Ok(stream) => {
+                debug!("Playing audio at {description}");
+                stream.play().map_err(|_| "Unable to start audio stream")?;
+                return Ok(stream);
             }
+            Err(e) => error!("Failed to create stream with {description}: {e}"),
         }
-    };
+    }
+    Err("Unable to create audio stream".to_string())
+}
 
-    let desired_config = StreamConfig {
-        channels: 2,
-        sample_rate: 48_000,
-        buffer_size: cpal::BufferSize::Default,
-    };
+fn build_stream(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    buffer: &AudioBuffer,
+    latency: Duration,
+) -> Result<Stream, BuildStreamError> {
+    match config.sample_format() {
+        SampleFormat::F32 => build::<f32>(device, config, buffer, latency),
+        SampleFormat::F64 => build::<f64>(device, config, buffer, latency),
+        SampleFormat::I32 => build::<i32>(device, config, buffer, latency),
+        SampleFormat::I16 => build::<i16>(device, config, buffer, latency),
+        SampleFormat::U32 => build::<u32>(device, config, buffer, latency),
+        SampleFormat::U16 => build::<u16>(device, config, buffer, latency),
+        SampleFormat::I8 => build::<i8>(device, config, buffer, latency),
+        SampleFormat::U8 => build::<u8>(device, config, buffer, latency),
+        _ => Err(BuildStreamError::StreamConfigNotSupported),
+    }
+}
 
-    let stream = match device.build_output_stream(
-        &desired_config,
-        audio_callback.clone(),
+fn build<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    buffer: &AudioBuffer,
+    latency: Duration,
+) -> Result<Stream, BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = usize::from(config.channels());
+    let frames = callback_frames(config);
+    let mut player = Player::new(buffer, latency, config.sample_rate(), frames);
+    device.build_output_stream(
+        &config.config(),
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for data in data.chunks_mut(frames * channels) {
+                let stereo = player.play(data.len() / channels);
+                write_frames(stereo, data, channels);
+            }
+        },
         |err| error!("Audio stream error: {err}"),
         None,
-    ) {
-        Ok(stream) => stream,
-        Err(e) => {
-            error!("Failed to create stream with 48000 Hz, trying 44100 Hz: {e}");
-            let fallback_config = StreamConfig {
-                channels: 2,
-                sample_rate: 44_100,
-                buffer_size: cpal::BufferSize::Default,
-            };
-            device
-                .build_output_stream(
-                    &fallback_config,
-                    audio_callback,
-                    |err| error!("Audio stream error: {err}"),
-                    None,
-                )
-                .expect("Failed to create audio stream")
+    )
+}
+
+// Frames of the largest callback of the device, up to `MAX_CALLBACK_FRAMES`
+fn callback_frames(config: &SupportedStreamConfig) -> usize {
+    match config.buffer_size() {
+        SupportedBufferSize::Range { max, .. } => usize::try_from(*max)
+            .unwrap_or(MAX_CALLBACK_FRAMES)
+            .clamp(1, MAX_CALLBACK_FRAMES),
+        SupportedBufferSize::Unknown => MAX_CALLBACK_FRAMES,
+    }
+}
+
+// Converts the stream from the rate of the detected machine to the device rate, a little
+// faster or slower to keep the latency. Runs in the audio callback, so it neither locks nor
+// allocates.
+struct Player {
+    buffer: AudioBuffer,
+    device_rate: f64,
+    controller: LatencyController,
+    resampler: Resampler,
+    stereo: Vec<f32>, // Frames of the largest callback, allocated up front
+}
+
+impl Player {
+    fn new(buffer: &AudioBuffer, latency: Duration, device_rate: u32, frames: usize) -> Self {
+        let device_rate = f64::from(device_rate);
+        Self {
+            buffer: buffer.clone(),
+            device_rate,
+            controller: LatencyController::new(latency),
+            resampler: Resampler::new(buffer.clone(), buffer.sample_rate(), device_rate),
+            stereo: vec![0.; 2 * frames],
         }
-    };
-    stream.play().map_err(|_| "Unable to start audio stream")?;
-    Ok(stream)
+    }
+
+    // Interleaved stereo samples for `frames` frames at the device rate, at most the frames
+    // the player was created for
+    fn play(&mut self, frames: usize) -> &[f32] {
+        let stream_rate = self.buffer.sample_rate();
+        let ratio = if self.buffer.is_starved() {
+            self.controller.restart();
+            self.controller.ratio()
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            let elapsed = frames as f64 / self.device_rate;
+            self.controller
+                .update(self.buffer.len(), stream_rate, elapsed)
+        };
+        self.resampler.set_stream_rate(stream_rate * ratio);
+
+        let stereo = &mut self.stereo[..2 * frames];
+        self.resampler.fill(stereo);
+        stereo
+    }
 }
@@ -0,0 +1,308 @@
+use clap::Parser;
+use socket2::SockRef;
+use std::iter::{self, Peekable};
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use lib::TimingProfile;
+use lib::capture::StreamKind;
+use lib::codec::SAMPLES_PER_PACKET;
+use lib::replay::open_capture;
+use lib::sim::{
+    Impairment, Scheduler, Tone, audio_packet, load_png, load_wav, test_pattern, video_packets,
+};
+
+/// C64 Ultimate stream simulator
+///
+/// Sends video and audio packets the way a Commodore 64 Ultimate cartridge does, so the viewer
+/// can be tested without one. Loss, reordering and jitter can be added to see how the viewer
+/// copes with a bad network.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Args {
+    /// Destination of the video stream, multicast or unicast
+    #[arg(long, default_value = "239.0.1.64:11000")]
+    video_dest: SocketAddrV4,
+    /// Destination of the audio stream, multicast or unicast
+    #[arg(long, default_value = "239.0.1.65:11001")]
+    audio_dest: SocketAddrV4,
+    /// Address of the interface to send multicast on (default: chosen by the OS)
+    #[arg(short, long)]
+    interface: Option<Ipv4Addr>,
+    /// Time to live of multicast packets
+    #[arg(long, default_value_t = 1)]
+    ttl: u32,
+    /// Send with NTSC instead of PAL timing
+    #[arg(long, default_value_t = false)]
+    ntsc: bool,
+    /// Send a PNG image, quantized to the C64 palette, instead of the test pattern
+    #[arg(long, value_name = "FILE", conflicts_with = "capture")]
+    image: Option<PathBuf>,
+    /// Send the video of a pcap, pcapng or capture file instead of the test pattern
+    #[arg(long, value_name = "FILE")]
+    capture: Option<PathBuf>,
+    /// Send a WAV file, repeated, instead of a tone
+    #[arg(long, value_name = "FILE")]
+    wav: Option<PathBuf>,
+    /// Frequency of the tone in Hz
+    #[arg(long, default_value_t = 440., conflicts_with = "wav")]
+    tone: f64,
+    /// Don't send audio
+    #[arg(long, default_value_t = false, conflicts_with = "wav")]
+    no_audio: bool,
+    /// Percentage of packets to drop
+    #[arg(long, value_parser = parse_percentage, default_value_t = 0.)]
+    loss: f64,
+    /// Percentage of packets to swap with the next packet of the stream
+    #[arg(long, value_parser = parse_percentage, default_value_t = 0.)]
+    reorder: f64,
+    /// Largest random delay added to a packet in milliseconds
+    #[arg(long, default_value_t = 0)]
+    jitter: u64,
+    /// Seed of the random loss, reordering and jitter
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Stop after this many seconds (default: run until stopped or the capture ends)
+    #[arg(long)]
+    duration: Option<u64>,
+}
+
+type Packets = Box<dyn Iterator<Item = Result<(Instant, Vec<u8>), String>>>;
+
+// Packets of one stream on their way to the socket
+struct Stream {
+    packets: Peekable<Packets>,
+    queue: Scheduler<Vec<u8>>,
+    dest: SocketAddrV4,
+    sent: u64,
+}
+
+impl Stream {
+    fn new(packets: Packets, dest: SocketAddrV4, impairment: Impairment, seed: u64) -> Self {
+        Self {
+            packets: packets.peekable(),
+            queue: Scheduler::new(impairment, seed),
+            dest,
+            sent: 0,
+        }
+    }
+
+    // Queues the packets generated up to `now` and sends the ones that are due
+    fn send(&mut self, socket: &UdpSocket, now: Instant) -> Result<(), String> {
+        while let Some(packet) = self
+            .packets
+            .next_if(|p| p.as_ref().map_or(true, |p| p.0 <= now))
+        {
+            let (due, packet) = packet?;
+            self.queue.push(due, packet);
+        }
+        if self.packets.peek().is_none() {
+            self.queue.flush(now);
+        }
+        while let Some(packet) = self.queue.pop(now) {
+            socket
+                .send_to(&packet, self.dest)
+                .map_err(|e| format!("Unable to send to {}: {e}", self.dest))?;
+            self.sent += 1;
+        }
+        Ok(())
+    }
+
+    fn next_wake(&mut self) -> Option<Instant> {
+        let generated = match self.packets.peek() {
+            Some(Ok((due, _))) => Some(*due),
+            Some(Err(_)) => Some(Instant::now()),
+            None => None,
+        };
+        generated.into_iter().chain(self.queue.next_due()).min()
+    }
+
+    fn is_done(&mut self) -> bool {
+        self.packets.peek().is_none() && self.queue.is_empty()
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let args = Args::parse();
+    let profile = if args.ntsc {
+        TimingProfile::Ntsc
+    } else {
+        TimingProfile::Pal
+    };
+    let impairment = Impairment {
+        loss: args.loss / 100.,
+        reorder: args.reorder / 100.,
+        jitter: Duration::from_millis(args.jitter),
+    };
+    let socket = sender_socket(&args)?;
+
+    let start = Instant::now();
+    let mut video = Stream::new(
+        video_stream(&args, profile, start)?,
+        args.video_dest,
+        impairment,
+        args.seed,
+    );
+    let mut audio = audio_stream(&args, profile, start)?.map(|packets| {
+        Stream::new(
+            packets,
+            args.audio_dest,
+            impairment,
+            args.seed.wrapping_add(1),
+        )
+    });
+    let end = args.duration.map(|secs| start + Duration::from_secs(secs));
+    println!(
+        "Sending {profile:?} video to {} and audio to {}",
+        args.video_dest,
+        audio
+            .as_ref()
+            .map_or("nowhere".to_string(), |a| a.dest.to_string())
+    );
+
+    loop {
+        let now = Instant::now();
+        if end.is_some_and(|end| now >= end) || video.is_done() {
+            break;
+        }
+        video.send(&socket, now)?;
+        if let Some(audio) = &mut audio {
+            audio.send(&socket, now)?;
+        }
+
+        let wake = iter::once(video.next_wake())
+            .chain(audio.as_mut().map(Stream::next_wake))
+            .chain(iter::once(end))
+            .flatten()
+            .min();
+        if let Some(wake) = wake {
+            thread::sleep(wake.saturating_duration_since(Instant::now()));
+        }
+    }
+
+    println!(
+        "Sent {} video and {} audio packets",
+        video.sent,
+        audio.map_or(0, |a| a.sent)
+    );
+    Ok(())
+}
+
+fn sender_socket(args: &Args) -> Result<UdpSocket, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .map_err(|e| format!("Unable to create socket: {e}"))?;
+    socket
+        .set_multicast_ttl_v4(args.ttl)
+        .map_err(|e| format!("Unable to set multicast TTL: {e}"))?;
+    if let Some(interface) = args.interface {
+        SockRef::from(&socket)
+            .set_multicast_if_v4(&interface)
+            .map_err(|e| format!("Unable to send multicast on {interface}: {e}"))?;
+    }
+    Ok(socket)
+}
+
+// Frames of the capture, the image or the test pattern, with the packets of a frame spread
+// over the frame period like the Ultimate does
+fn video_stream(args: &Args, profile: TimingProfile, start: Instant) -> Result<Packets, String> {
+    if let Some(path) = &args.capture {
+        let datagrams = open_capture(path, args.video_dest.port(), args.audio_dest.port())?;
+        let mut first = None;
+        return Ok(Box::new(datagrams.filter_map(
+            move |datagram| match datagram {
+                Ok(datagram) if datagram.kind == StreamKind::Video => {
+                    let offset = datagram
+                        .time
+                        .saturating_sub(*first.get_or_insert(datagram.time));
+                    Some(Ok((start + offset, datagram.data)))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            },
+        )));
+    }
+
+    let lines = profile.visible_lines();
+    let image = args
+        .image
+        .as_deref()
+        .map(|path| load_png(path, lines))
+        .transpose()?;
+    let period = profile.frame_period();
+    let mut seq = 0u16;
+    let frames = iter::successors(Some(0u16), |frame| Some(frame.wrapping_add(1)));
+    Ok(Box::new(frames.zip(0u32..).flat_map(move |(frame, n)| {
+        let pixels = image.clone().unwrap_or_else(|| test_pattern(frame, lines));
+        let packets = video_packets(&pixels, frame, &mut seq);
+        let count = u32::try_from(packets.len()).unwrap_or(u32::MAX);
+        let frame_start = start + period * n;
+        (0u32..)
+            .zip(packets)
+            .map(|(i, packet)| Ok((frame_start + period * i / count, packet)))
+            .collect::<Vec<_>>()
+    })))
+}
+
+// Tone or WAV file, `None` without audio
+fn audio_stream(
+    args: &Args,
+    profile: TimingProfile,
+    start: Instant,
+) -> Result<Option<Packets>, String> {
+    if args.no_audio {
+        return Ok(None);
+    }
+    let rate = profile.sample_rate();
+    let mut samples: Box<dyn FnMut() -> [[i16; 2]; SAMPLES_PER_PACKET]> =
+        if let Some(path) = &args.wav {
+            let (wav, wav_rate) = load_wav(path)?;
+            if wav.is_empty() {
+                return Err(format!("{} has no samples", path.display()));
+            }
+            if (f64::from(wav_rate) - rate).abs() > 100. {
+                eprintln!(
+                    "Warning: {} has a sample rate of {wav_rate} Hz, it is sent at {rate:.0} Hz",
+                    path.display()
+                );
+            }
+            let mut position = 0;
+            Box::new(move || {
+                std::array::from_fn(|_| {
+                    let pair = wav[position];
+                    position = (position + 1) % wav.len();
+                    pair
+                })
+            })
+        } else {
+            debug!("Sending a {} Hz tone", args.tone);
+            let mut tone = Tone::new(args.tone, rate);
+            Box::new(move || tone.next_packet())
+        };
+
+    let period = Duration::from_secs_f64(
+        f64::from(u32::try_from(SAMPLES_PER_PACKET).unwrap_or_default()) / rate,
+    );
+    let seqs = iter::successors(Some(0u16), |seq| Some(seq.wrapping_add(1)));
+    Ok(Some(Box::new(seqs.zip(0u32..).map(move |(seq, n)| {
+        Ok((start + period * n, audio_packet(seq, &samples())))
+    }))))
+}
+
+fn parse_percentage(s: &str) -> Result<f64, String> {
+    let value = s
+        .trim()
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid percentage '{s}'"))?;
+    if !(0. ..=100.).contains(&value) {
+        return Err(format!("Percentage must be between 0 and 100, got {s}"));
+    }
+    Ok(value)
+}
@@ -0,0 +1,83 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use zerocopy::byteorder::little_endian::{U16, U32, U64};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+pub const MAGIC: [u8; 8] = *b"U64CAPT\0";
+pub const FOOTER_MAGIC: [u8; 8] = *b"U64INDX\0";
+pub const VERSION: u16 = 1;
+
+/// Stream a datagram was received on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamKind {
+    Video,
+    Audio,
+}
+
+impl StreamKind {
+    #[must_use]
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Self::Video => 0,
+            Self::Audio => 1,
+        }
+    }
+
+    #[must_use]
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Video),
+            1 => Some(Self::Audio),
+            _ => None,
+        }
+    }
+}
+
+/// Start of a capture file
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct FileHeader {
+    pub magic: [u8; 8],
+    pub version: U16,
+    pub reserved: U16,
+    pub index_interval: U32, // Microseconds between index entries
+    pub start_time: U64,     // Microseconds since the UNIX epoch
+}
+
+/// Precedes every datagram in the file
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct RecordHeader {
+    pub time: U64,     // Microseconds since the start of the recording
+    pub len: U16,      // Length of the datagram that follows
+    pub kind: u8,      // `StreamKind`
+    pub reserved: u8,  // Always zero
+    pub addr: [u8; 4], // IPv4 address of the sender
+    pub port: U16,     // Port of the sender
+}
+
+impl RecordHeader {
+    #[must_use]
+    pub fn source(&self) -> SocketAddr {
+        SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::from(self.addr),
+            self.port.get(),
+        ))
+    }
+}
+
+/// Position of the first record at or after `time`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct IndexEntry {
+    pub time: U64,   // Microseconds since the start of the recording
+    pub offset: U64, // Offset of the record from the start of the file
+}
+
+/// End of a capture file that was closed properly, the index precedes it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct Footer {
+    pub index_offset: U64, // Offset of the first index entry
+    pub records: U64,      // Number of records in the file
+    pub magic: [u8; 8],
+}
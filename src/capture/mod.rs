@@ -0,0 +1,11 @@
+mod format;
+mod reader;
+mod recorder;
+mod writer;
+
+pub use format::{
+    FOOTER_MAGIC, FileHeader, Footer, IndexEntry, MAGIC, RecordHeader, StreamKind, VERSION,
+};
+pub use reader::CaptureReader;
+pub use recorder::{Recorder, Recording, capture_file_name};
+pub use writer::CaptureWriter;
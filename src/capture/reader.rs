@@ -0,0 +1,134 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+use zerocopy::{FromBytes, IntoBytes};
+
+use super::format::{FOOTER_MAGIC, FileHeader, Footer, IndexEntry, MAGIC, RecordHeader, VERSION};
+
+/// Reads the records of a capture file
+pub struct CaptureReader<R: Read + Seek> {
+    inner: R,
+    header: FileHeader,
+    index: Vec<IndexEntry>,
+    position: u64, // Offset of the next record
+    end: u64,      // Offset of the index, or the file length if the recording was not finished
+}
+
+impl<R: Read + Seek> CaptureReader<R> {
+    /// # Errors
+    /// Returns an error if this is not a capture file, its version is not supported or it can't
+    /// be read
+    pub fn new(mut inner: R) -> Result<Self, String> {
+        let header: FileHeader =
+            read(&mut inner).map_err(|e| format!("Not a capture file: {e}"))?;
+        if header.magic != MAGIC {
+            return Err("Not a capture file".to_string());
+        }
+        if header.version.get() > VERSION {
+            return Err(format!(
+                "Unsupported capture file version {}",
+                header.version.get()
+            ));
+        }
+        let position = size_of::<FileHeader>() as u64;
+        let len = inner.seek(SeekFrom::End(0)).map_err(|e| e.to_string())?;
+        let mut end = len;
+        let mut index = Vec::new();
+
+        // A recording that was not stopped properly has no index
+        let footer_offset = len.checked_sub(size_of::<Footer>() as u64);
+        if let Some(footer_offset) = footer_offset.filter(|&offset| offset >= position) {
+            inner
+                .seek(SeekFrom::Start(footer_offset))
+                .map_err(|e| e.to_string())?;
+            let footer: Footer = read(&mut inner).map_err(|e| e.to_string())?;
+            let index_offset = footer.index_offset.get();
+            if footer.magic == FOOTER_MAGIC && (position..=footer_offset).contains(&index_offset) {
+                let entries = (footer_offset - index_offset) / size_of::<IndexEntry>() as u64;
+                inner
+                    .seek(SeekFrom::Start(index_offset))
+                    .map_err(|e| e.to_string())?;
+                for _ in 0..entries {
+                    index.push(read(&mut inner).map_err(|e| e.to_string())?);
+                }
+                end = index_offset;
+            }
+        }
+
+        inner
+            .seek(SeekFrom::Start(position))
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            inner,
+            header,
+            index,
+            position,
+            end,
+        })
+    }
+
+    /// Time the recording started since the UNIX epoch
+    #[must_use]
+    pub fn start_time(&self) -> Duration {
+        Duration::from_micros(self.header.start_time.get())
+    }
+
+    #[must_use]
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    /// Continues at the last index entry at or before `time` since the start of the recording
+    ///
+    /// # Errors
+    /// Returns an error if seeking fails
+    pub fn seek(&mut self, time: Duration) -> Result<(), String> {
+        let time = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+        let entry = self.index.partition_point(|entry| entry.time.get() <= time);
+        self.position = entry
+            .checked_sub(1)
+            .map_or(size_of::<FileHeader>() as u64, |i| {
+                self.index[i].offset.get()
+            });
+        self.inner
+            .seek(SeekFrom::Start(self.position))
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Reads the next record, `None` after the last one. A truncated last record, left by a
+    /// recording that was cut off, is skipped.
+    ///
+    /// # Errors
+    /// Returns an error if reading fails
+    pub fn next_record(&mut self) -> Result<Option<(RecordHeader, Vec<u8>)>, String> {
+        let header_len = size_of::<RecordHeader>() as u64;
+        if self.position + header_len > self.end {
+            return Ok(None);
+        }
+        let header: RecordHeader = read(&mut self.inner).map_err(|e| e.to_string())?;
+        let len = header.len.get();
+        if self.position + header_len + u64::from(len) > self.end {
+            return Ok(None);
+        }
+        let mut data = vec![0u8; usize::from(len)];
+        self.inner
+            .read_exact(&mut data)
+            .map_err(|e| e.to_string())?;
+        self.position += header_len + u64::from(len);
+        Ok(Some((header, data)))
+    }
+}
+
+impl<R: Read + Seek> Iterator for CaptureReader<R> {
+    type Item = Result<(RecordHeader, Vec<u8>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+fn read<T: FromBytes + IntoBytes, R: Read>(reader: &mut R) -> io::Result<T> {
+    let mut value = T::new_zeroed();
+    reader.read_exact(value.as_mut_bytes())?;
+    Ok(value)
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+use super::{CaptureWriter, StreamKind};
+
+pub type Recording = Arc<Mutex<Recorder>>;
+
+/// Records the datagrams of both streams to a capture file while started
+#[derive(Default)]
+pub struct Recorder {
+    capture: Option<(PathBuf, CaptureWriter<BufWriter<File>>)>,
+}
+
+impl Recorder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts recording to `path`, a running recording is stopped first
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be created or written
+    pub fn start(&mut self, path: &Path) -> Result<(), String> {
+        self.stop()?;
+        let file = File::create(path)
+            .map_err(|e| format!("Unable to create capture file {}: {e}", path.display()))?;
+        let writer = CaptureWriter::new(BufWriter::new(file), Instant::now())
+            .map_err(|e| format!("Unable to write capture file {}: {e}", path.display()))?;
+        debug!("Recording to {}", path.display());
+        self.capture = Some((path.to_path_buf(), writer));
+        Ok(())
+    }
+
+    /// Stops recording and returns the path of the capture file, if recording
+    ///
+    /// # Errors
+    /// Returns an error if the index can't be written
+    pub fn stop(&mut self) -> Result<Option<PathBuf>, String> {
+        let Some((path, writer)) = self.capture.take() else {
+            return Ok(None);
+        };
+        let records = writer.records();
+        writer
+            .finish()
+            .map_err(|e| format!("Unable to finish capture file {}: {e}", path.display()))?;
+        debug!("Recorded {records} datagrams to {}", path.display());
+        Ok(Some(path))
+    }
+
+    /// Path of the capture file, if recording
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.capture.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Records a `datagram` received on `kind` from `src` at `now` if recording. Recording stops
+    /// when the file can't be written.
+    pub fn record(&mut self, now: Instant, kind: StreamKind, src: SocketAddr, datagram: &[u8]) {
+        if let Some((path, writer)) = &mut self.capture
+            && let Err(e) = writer.write(now, kind, src, datagram)
+        {
+            debug!("Stopped recording to {}: {e}", path.display());
+            self.capture = None;
+        }
+    }
+}
+
+/// Name of the capture file for a recording started now, e.g. `u64viewer-1767225600.u64cap`
+#[must_use]
+pub fn capture_file_name() -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    PathBuf::from(format!("u64viewer-{secs}.u64cap"))
+}
@@ -0,0 +1,121 @@
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zerocopy::IntoBytes;
+
+use super::StreamKind;
+use super::format::{FOOTER_MAGIC, FileHeader, Footer, IndexEntry, MAGIC, RecordHeader, VERSION};
+
+const INDEX_INTERVAL: u64 = 1_000_000; // Microseconds between index entries
+
+/// Writes datagrams to a capture file
+///
+/// The file starts with a `FileHeader`, followed by a `RecordHeader` and the datagram for every
+/// record. `finish` appends an index with an entry per second and a `Footer`.
+pub struct CaptureWriter<W: Write> {
+    inner: W,
+    start: Instant, // Time of the first record
+    offset: u64,    // Bytes written so far
+    records: u64,   // Records written so far
+    index: Vec<IndexEntry>,
+    next_index: u64, // Time of the next index entry
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Writes the file header, record times are relative to `start`
+    ///
+    /// # Errors
+    /// Returns an error if writing fails
+    pub fn new(mut inner: W, start: Instant) -> io::Result<Self> {
+        let start_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, micros);
+        let header = FileHeader {
+            magic: MAGIC,
+            version: VERSION.into(),
+            reserved: 0.into(),
+            index_interval: u32::try_from(INDEX_INTERVAL).unwrap_or(u32::MAX).into(),
+            start_time: start_time.into(),
+        };
+        inner.write_all(header.as_bytes())?;
+        Ok(Self {
+            inner,
+            start,
+            offset: header.as_bytes().len() as u64,
+            records: 0,
+            index: Vec::new(),
+            next_index: 0,
+        })
+    }
+
+    /// Appends a `datagram` received on `kind` from `src` at `now`
+    ///
+    /// # Errors
+    /// Returns an error if the datagram is longer than 65535 bytes or writing fails
+    pub fn write(
+        &mut self,
+        now: Instant,
+        kind: StreamKind,
+        src: SocketAddr,
+        datagram: &[u8],
+    ) -> io::Result<()> {
+        let len = u16::try_from(datagram.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Datagram too long"))?;
+        let time = micros(now.saturating_duration_since(self.start));
+        if time >= self.next_index {
+            self.index.push(IndexEntry {
+                time: time.into(),
+                offset: self.offset.into(),
+            });
+            self.next_index = time - time % INDEX_INTERVAL + INDEX_INTERVAL;
+        }
+
+        // The sockets are IPv4 only
+        let addr = match src {
+            SocketAddr::V4(src) => *src.ip(),
+            SocketAddr::V6(src) => src.ip().to_ipv4_mapped().unwrap_or(Ipv4Addr::UNSPECIFIED),
+        };
+        let header = RecordHeader {
+            time: time.into(),
+            len: len.into(),
+            kind: kind.to_byte(),
+            reserved: 0,
+            addr: addr.octets(),
+            port: src.port().into(),
+        };
+        self.inner.write_all(header.as_bytes())?;
+        self.inner.write_all(datagram)?;
+        self.offset += (header.as_bytes().len() + datagram.len()) as u64;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Number of records written so far
+    #[must_use]
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Writes the index and footer and returns the underlying writer
+    ///
+    /// # Errors
+    /// Returns an error if writing fails
+    pub fn finish(mut self) -> io::Result<W> {
+        for entry in &self.index {
+            self.inner.write_all(entry.as_bytes())?;
+        }
+        let footer = Footer {
+            index_offset: self.offset.into(),
+            records: self.records.into(),
+            magic: FOOTER_MAGIC,
+        };
+        self.inner.write_all(footer.as_bytes())?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+// Saturates after half a million years
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,361 @@
+use std::borrow::Cow;
+use std::fmt;
+use zerocopy::byteorder::little_endian::{I16, U16};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+use crate::constants::{MAX_HEIGHT, MAX_LPP, MAX_WIDTH};
+
+pub const LAST_LINE: u16 = 0x8000; // End-of-frame marker in the line field
+pub const SAMPLES_PER_PACKET: usize = 192; // Left and right sample pairs per audio packet
+
+/// Header of a video packet as sent on the wire, all fields little-endian
+#[repr(C)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned,
+)]
+pub struct VideoHeader {
+    seq: U16,      // Sequence number
+    frame: U16,    // Frame number
+    line: U16,     // Line number
+    width: U16,    // Pixels per line (384 on the Ultimate)
+    lpp: u8,       // Lines per packet (4 on the Ultimate)
+    bits: u8,      // Bit per pixel (4 on the Ultimate)
+    encoding: U16, // Encoding type (0=no encoding, 1=RLE encoding)
+}
+
+impl VideoHeader {
+    /// Size of the header on the wire
+    pub const SIZE: usize = size_of::<Self>();
+
+    #[must_use]
+    pub fn new(seq: u16, frame: u16, line: u16, width: u16, lpp: u8, bits: u8) -> Self {
+        Self {
+            seq: seq.into(),
+            frame: frame.into(),
+            line: line.into(),
+            width: width.into(),
+            lpp,
+            bits,
+            encoding: U16::ZERO,
+        }
+    }
+
+    /// Sets the end-of-frame marker
+    #[must_use]
+    pub fn last_line(mut self) -> Self {
+        self.line = (self.line.get() | LAST_LINE).into();
+        self
+    }
+
+    #[must_use]
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = u16::from(encoding).into();
+        self
+    }
+
+    #[must_use]
+    pub fn seq(&self) -> u16 {
+        self.seq.get()
+    }
+
+    #[must_use]
+    pub fn frame(&self) -> u16 {
+        self.frame.get()
+    }
+
+    /// Line field including the end-of-frame marker
+    #[must_use]
+    pub fn line(&self) -> u16 {
+        self.line.get()
+    }
+
+    /// First line of the packet
+    #[must_use]
+    pub fn line_index(&self) -> u16 {
+        self.line.get() & !LAST_LINE
+    }
+
+    /// The packet holds the last lines of the frame
+    #[must_use]
+    pub fn is_last_line(&self) -> bool {
+        self.line.get() & LAST_LINE != 0
+    }
+
+    #[must_use]
+    pub fn width(&self) -> u16 {
+        self.width.get()
+    }
+
+    #[must_use]
+    pub fn lpp(&self) -> u8 {
+        self.lpp
+    }
+
+    #[must_use]
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Encoding type as sent, see `Encoding`
+    #[must_use]
+    pub fn encoding_type(&self) -> u16 {
+        self.encoding.get()
+    }
+
+    /// Bytes of pixel data in the packet once decoded
+    #[must_use]
+    pub fn payload_size(&self) -> usize {
+        usize::from(self.width.get()) * usize::from(self.lpp) * usize::from(self.bits) / 8
+    }
+
+    /// Serializes the header followed by `payload`, which must already be in the encoding of
+    /// the header
+    #[must_use]
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut datagram = Vec::with_capacity(Self::SIZE + payload.len());
+        datagram.extend_from_slice(self.as_bytes());
+        datagram.extend_from_slice(payload);
+        datagram
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Raw,
+    Rle,
+}
+
+impl TryFrom<u16> for Encoding {
+    type Error = PacketError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Raw),
+            1 => Ok(Self::Rle),
+            _ => Err(PacketError::Encoding(value)),
+        }
+    }
+}
+
+impl From<Encoding> for u16 {
+    fn from(encoding: Encoding) -> Self {
+        match encoding {
+            Encoding::Raw => 0,
+            Encoding::Rle => 1,
+        }
+    }
+}
+
+/// Reason a datagram was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PacketError {
+    Length(usize), // Datagram length does not match the header
+    Width(u16),    // Unsupported pixels per line
+    Lpp(u8),       // Unsupported lines per packet
+    Line(u16),     // Lines of the packet go past the largest frame
+    Bits(u8),      // Unsupported bits per pixel
+    Encoding(u16), // Unknown encoding type
+    Rle,           // RLE payload does not decode to one packet of pixels
+}
+
+impl PacketError {
+    /// Short name of the reason, used to count drops
+    #[must_use]
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Length(_) => "length",
+            Self::Width(_) => "width",
+            Self::Lpp(_) => "lpp",
+            Self::Line(_) => "line",
+            Self::Bits(_) => "bits",
+            Self::Encoding(_) => "encoding",
+            Self::Rle => "rle",
+        }
+    }
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length(len) => write!(f, "unexpected datagram length {len}"),
+            Self::Width(width) => write!(f, "unsupported width {width}"),
+            Self::Lpp(lpp) => write!(f, "unsupported lines per packet {lpp}"),
+            Self::Line(line) => write!(f, "line {line} out of range"),
+            Self::Bits(bits) => write!(f, "unsupported bits per pixel {bits}"),
+            Self::Encoding(encoding) => write!(f, "unknown encoding {encoding}"),
+            Self::Rle => write!(f, "invalid RLE data"),
+        }
+    }
+}
+
+/// A video packet with its pixel data decoded to packed pixels
+#[derive(Debug, Clone)]
+pub struct VideoPacket<'a> {
+    pub seq: u16,
+    pub frame: u16,
+    pub line: u16, // Including the end-of-frame marker
+    pub width: u16,
+    pub lpp: u8,
+    pub bits: u8,
+    pub data: Cow<'a, [u8]>,
+}
+
+impl VideoPacket<'_> {
+    /// First line of the packet
+    #[must_use]
+    pub fn line_index(&self) -> u16 {
+        self.line & !LAST_LINE
+    }
+
+    /// The packet holds the last lines of the frame
+    #[must_use]
+    pub fn is_last_line(&self) -> bool {
+        self.line & LAST_LINE != 0
+    }
+
+    /// Header of the packet, without encoding
+    #[must_use]
+    pub fn header(&self) -> VideoHeader {
+        VideoHeader::new(
+            self.seq, self.frame, self.line, self.width, self.lpp, self.bits,
+        )
+    }
+
+    /// Serializes the packet without encoding
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        self.header().encode(&self.data)
+    }
+
+    /// Serializes the packet with RLE encoding
+    #[must_use]
+    pub fn encode_rle(&self) -> Vec<u8> {
+        self.header()
+            .encoding(Encoding::Rle)
+            .encode(&encode_rle(&self.data))
+    }
+}
+
+/// Parses a video datagram and decodes its payload according to the encoding in the header
+///
+/// # Errors
+/// Returns an error if the length does not match the header, the geometry is unsupported, the
+/// encoding is unknown or the payload does not decode to exactly one packet worth of pixels
+pub fn decode_video(datagram: &[u8]) -> Result<VideoPacket<'_>, PacketError> {
+    let (header, payload) =
+        VideoHeader::ref_from_prefix(datagram).map_err(|_| PacketError::Length(datagram.len()))?;
+
+    if header.bits != 4 && header.bits != 8 {
+        return Err(PacketError::Bits(header.bits));
+    }
+    let width = usize::from(header.width());
+    if width == 0 || width > MAX_WIDTH || width * usize::from(header.bits) % 8 != 0 {
+        return Err(PacketError::Width(header.width()));
+    }
+    if header.lpp == 0 || header.lpp > MAX_LPP {
+        return Err(PacketError::Lpp(header.lpp));
+    }
+    if usize::from(header.line_index()) + usize::from(header.lpp) > MAX_HEIGHT {
+        return Err(PacketError::Line(header.line_index()));
+    }
+    let size = header.payload_size();
+
+    let data = match Encoding::try_from(header.encoding_type())? {
+        Encoding::Raw => {
+            if payload.len() != size {
+                return Err(PacketError::Length(datagram.len()));
+            }
+            Cow::Borrowed(payload)
+        }
+        Encoding::Rle => Cow::Owned(decode_rle(payload, size)?),
+    };
+
+    Ok(VideoPacket {
+        seq: header.seq(),
+        frame: header.frame(),
+        line: header.line(),
+        width: header.width(),
+        lpp: header.lpp,
+        bits: header.bits,
+        data,
+    })
+}
+
+/// Decodes an RLE payload made of `(count, value)` byte pairs into `size` bytes
+///
+/// # Errors
+/// Returns an error on a zero run length, a dangling count byte or if the runs do not add up
+/// to exactly `size` bytes
+pub fn decode_rle(payload: &[u8], size: usize) -> Result<Vec<u8>, PacketError> {
+    let mut data = Vec::with_capacity(size);
+    let mut pairs = payload.chunks_exact(2);
+
+    for pair in &mut pairs {
+        let (count, value) = (usize::from(pair[0]), pair[1]);
+        if count == 0 || data.len() + count > size {
+            return Err(PacketError::Rle);
+        }
+        data.resize(data.len() + count, value);
+    }
+
+    if !pairs.remainder().is_empty() || data.len() != size {
+        return Err(PacketError::Rle);
+    }
+    Ok(data)
+}
+
+/// Encodes `data` as `(count, value)` byte pairs, runs longer than 255 bytes are split
+#[must_use]
+pub fn encode_rle(data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for run in data.chunk_by(|a, b| a == b) {
+        for part in run.chunks(usize::from(u8::MAX)) {
+            payload.push(u8::try_from(part.len()).unwrap_or(u8::MAX));
+            payload.push(part[0]);
+        }
+    }
+    payload
+}
+
+/// Parses an audio datagram
+///
+/// # Errors
+/// Returns an error if the datagram is not exactly one audio packet long
+pub fn decode_audio(datagram: &[u8]) -> Result<AudioStream, PacketError> {
+    AudioStream::read_from_bytes(datagram).map_err(|_| PacketError::Length(datagram.len()))
+}
+
+/// An audio packet as sent on the wire, all fields little-endian
+#[repr(C)]
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct AudioStream {
+    seq: U16,                             // Sequence number
+    data: [[I16; 2]; SAMPLES_PER_PACKET], // Left channel, Right channel
+}
+
+impl AudioStream {
+    #[must_use]
+    pub fn new(seq: u16, samples: &[[i16; 2]; SAMPLES_PER_PACKET]) -> Self {
+        Self {
+            seq: seq.into(),
+            data: samples.map(|pair| pair.map(I16::new)),
+        }
+    }
+
+    #[must_use]
+    pub fn seq(&self) -> u16 {
+        self.seq.get()
+    }
+
+    /// Left and right sample pairs
+    #[must_use]
+    pub fn samples(&self) -> [[i16; 2]; SAMPLES_PER_PACKET] {
+        self.data.map(|pair| pair.map(I16::get))
+    }
+
+    /// Serializes the packet
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
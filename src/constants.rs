@@ -1,5 +1,8 @@
 pub const WIDTH: usize = 384;
 pub const HEIGHT: usize = 272;
+pub const MAX_WIDTH: usize = 2048;
+pub const MAX_HEIGHT: usize = 1024;
+pub const MAX_LPP: u8 = 16;
 pub const COLORS: [[u8; 4]; 16] = [
     [0x00, 0x00, 0x00, 0x00],
     [0x00, 0xEF, 0xEF, 0xEF],
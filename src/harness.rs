@@ -0,0 +1,223 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket as StdUdpSocket};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::codec::SAMPLES_PER_PACKET;
+use crate::network::{Interface, NetworkConfig, NetworkInput};
+use crate::{
+    AudioBuffer, Frame, Recorder, SampleQueue, Sink, Statistics, Stats, StatsSnapshot,
+    StreamSnapshot, TimingDetector, WavRecording,
+};
+
+const WAIT: Duration = Duration::from_secs(2); // Longest wait for a frame or samples
+const VIDEO_GROUP: Ipv4Addr = Ipv4Addr::new(239, 0, 1, 64);
+const AUDIO_GROUP: Ipv4Addr = Ipv4Addr::new(239, 0, 1, 65);
+
+/// Runs the network receive path on the loopback interface and feeds it crafted packets
+///
+/// The streams are received on free ports with the sockets, decoders and handlers used by
+/// `network_tasks`, but with a cancellation token of their own, so every test gets a fresh
+/// receiver. The frames and samples it produces are collected for checking.
+pub struct Harness {
+    sender: UdpSocket,
+    video: SocketAddr,
+    audio: SocketAddr,
+    video_rx: Receiver<Frame>,
+    audio_buffer: AudioBuffer,
+    stats: Stats,
+    cancel: CancellationToken,
+    task: JoinHandle<Result<(), String>>,
+}
+
+impl Harness {
+    /// Starts receiving, the sockets are bound when this returns
+    ///
+    /// # Errors
+    /// Returns an error if the sockets can't be bound or the groups joined on the loopback
+    /// interface
+    pub async fn start() -> Result<Self, String> {
+        Self::start_with(None, Vec::new(), WAIT).await
+    }
+
+    /// Starts receiving from the `allowed` senders only, the streams are lost after `timeout`
+    /// without their packets
+    ///
+    /// # Errors
+    /// Returns an error if the sockets can't be bound or the groups joined on the loopback
+    /// interface
+    pub async fn start_allowing(allowed: Vec<IpAddr>, timeout: Duration) -> Result<Self, String> {
+        Self::start_with(None, allowed, timeout).await
+    }
+
+    /// Starts receiving muted, recording the audio to `wav` instead of the audio buffer
+    ///
+    /// # Errors
+    /// Returns an error if the sockets can't be bound or the groups joined on the loopback
+    /// interface
+    pub async fn start_recording(wav: WavRecording) -> Result<Self, String> {
+        Self::start_with(Some(wav), Vec::new(), WAIT).await
+    }
+
+    async fn start_with(
+        wav: Option<WavRecording>,
+        allowed_sources: Vec<IpAddr>,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        let (video_port, audio_port) = (free_port()?, free_port()?);
+        let config = NetworkConfig {
+            video_maddr: VIDEO_GROUP,
+            audio_maddr: AUDIO_GROUP,
+            video_port,
+            audio_port,
+            timeout,
+            interface: Some(Interface::Address(Ipv4Addr::LOCALHOST)),
+            recv_buffer: 1024 * 1024,
+            allowed_sources,
+            demux: false,
+            source: None,
+        };
+
+        let (video_tx, video_rx) = mpsc::channel(16);
+        let audio_buffer = Arc::new(SampleQueue::new(48_000, 0));
+        let stats = Arc::new(Mutex::new(Statistics::new()));
+        let sink = Sink {
+            video_tx,
+            audio_buffer: wav.is_none().then(|| audio_buffer.clone()),
+            wav,
+            timing: Arc::new(Mutex::new(TimingDetector::new())),
+            stats: stats.clone(),
+        };
+        let cancel = CancellationToken::new();
+        let recording = Arc::new(Mutex::new(Recorder::new()));
+        let receive = NetworkInput::new(config, recording).listen(sink, cancel.clone())?;
+
+        let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .map_err(|e| format!("Unable to bind sender: {e}"))?;
+        Ok(Self {
+            sender,
+            video: SocketAddr::from((Ipv4Addr::LOCALHOST, video_port)),
+            audio: SocketAddr::from((Ipv4Addr::LOCALHOST, audio_port)),
+            video_rx,
+            audio_buffer,
+            stats,
+            cancel,
+            task: tokio::spawn(receive),
+        })
+    }
+
+    /// Sends a datagram to the video port
+    ///
+    /// # Errors
+    /// Returns an error if sending fails
+    pub async fn send_video(&self, datagram: &[u8]) -> Result<(), String> {
+        send(&self.sender, self.video, datagram).await
+    }
+
+    /// Sends a datagram to the audio port
+    ///
+    /// # Errors
+    /// Returns an error if sending fails
+    pub async fn send_audio(&self, datagram: &[u8]) -> Result<(), String> {
+        send(&self.sender, self.audio, datagram).await
+    }
+
+    /// Address of the video port, for senders of their own
+    #[must_use]
+    pub fn video_addr(&self) -> SocketAddr {
+        self.video
+    }
+
+    /// Waits for the next completed frame, `None` if none arrives in time
+    pub async fn frame(&mut self) -> Option<Frame> {
+        tokio::time::timeout(WAIT, self.video_rx.recv())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Waits until `count` samples are queued, or no more arrive in time, and takes all queued
+    /// samples
+    pub async fn samples(&self, count: usize) -> Vec<f32> {
+        let start = Instant::now();
+        while self.queued() < count && start.elapsed() < WAIT {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let mut samples = vec![0.; self.audio_buffer.len()];
+        self.audio_buffer.pop_slice(&mut samples);
+        samples
+    }
+
+    /// Waits until the packets sent so far have been counted, or no more arrive in time
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `stats` lock
+    pub async fn settle(&self, video: u64, audio: u64) -> StatsSnapshot {
+        let start = Instant::now();
+        loop {
+            let stats = self.stats();
+            let counted = |s: &StreamSnapshot| s.received + s.malformed;
+            if (counted(&stats.video) >= video && counted(&stats.audio) >= audio)
+                || start.elapsed() >= WAIT
+            {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    }
+
+    /// # Panics
+    /// Panics if unable to acquire a `stats` lock
+    #[must_use]
+    pub fn stats(&self) -> StatsSnapshot {
+        self.stats
+            .lock()
+            .expect("Unable to acquire lock on stats")
+            .snapshot()
+    }
+
+    /// Stops receiving
+    ///
+    /// # Errors
+    /// Returns an error if the receive path failed
+    pub async fn stop(self) -> Result<(), String> {
+        self.cancel.cancel();
+        self.task
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    fn queued(&self) -> usize {
+        self.audio_buffer.len()
+    }
+}
+
+/// Samples the receive path queues for the sample pairs of one audio packet
+#[must_use]
+pub fn expected_samples(samples: &[[i16; 2]; SAMPLES_PER_PACKET]) -> Vec<f32> {
+    samples
+        .iter()
+        .flatten()
+        .map(|&sample| f32::from(sample) / 32768.)
+        .collect()
+}
+
+async fn send(socket: &UdpSocket, dest: SocketAddr, datagram: &[u8]) -> Result<(), String> {
+    socket
+        .send_to(datagram, dest)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Unable to send to {dest}: {e}"))
+}
+
+// Port that is not in use, the receive sockets allow sharing it anyway
+fn free_port() -> Result<u16, String> {
+    StdUdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| format!("Unable to find a free port: {e}"))
+}
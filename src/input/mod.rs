@@ -0,0 +1,54 @@
+mod synthetic;
+
+pub use synthetic::SyntheticInput;
+
+use std::pin::Pin;
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+
+use crate::network::{AudioDecoder, Filter, VideoDecoder};
+use crate::{AudioBuffer, Frame, Stats, Timing, WavRecording};
+
+pub type InputFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// Where the frames and samples of an input end up
+pub struct Sink {
+    pub video_tx: Sender<Frame>,
+    pub audio_buffer: Option<AudioBuffer>, // If muted `audio_buffer` is `None`
+    pub wav: Option<WavRecording>,         // If not recording to a WAV file `wav` is `None`
+    pub timing: Timing,
+    pub stats: Stats,
+}
+
+impl Sink {
+    /// Decoders that feed this sink, the audio decoder is `None` when muted and not recording
+    /// to a WAV file
+    #[must_use]
+    pub fn decoders(&self, filter: &Filter) -> (VideoDecoder, Option<AudioDecoder>) {
+        let video = VideoDecoder::new(filter.clone(), self.timing.clone(), self.stats.clone());
+        let audio = (self.audio_buffer.is_some() || self.wav.is_some()).then(|| {
+            AudioDecoder::new(
+                filter.clone(),
+                self.audio_buffer.clone(),
+                self.wav.clone(),
+                self.timing.clone(),
+                self.stats.clone(),
+            )
+        });
+        (video, audio)
+    }
+}
+
+/// A source of video frames and audio samples: the network, a capture file or a generator
+///
+/// Everything behind the `Sink` is the same whatever the input, so new inputs only have to
+/// produce datagrams or frames.
+pub trait Input: Send {
+    /// Feeds `sink` until `cancel` is cancelled
+    ///
+    /// An input that runs out keeps waiting for `cancel`, so the last frame stays on screen.
+    ///
+    /// # Errors
+    /// Returns an error if the input can't be opened or fails while running
+    fn run(self: Box<Self>, sink: Sink, cancel: CancellationToken) -> InputFuture;
+}
@@ -0,0 +1,87 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use super::{Input, InputFuture, Sink};
+use crate::TimingProfile;
+use crate::codec::SAMPLES_PER_PACKET;
+use crate::network::SourceFilter;
+use crate::sim::{Tone, audio_packet, test_pattern, video_packets};
+
+const TONE: f64 = 440.; // Frequency of the test tone in Hz
+
+/// Generates a test pattern and a test tone with the timing of a PAL or NTSC machine
+///
+/// The packets go through the same decoders as received ones, so the statistics, timing
+/// detection and on-screen display work without an Ultimate.
+pub struct SyntheticInput {
+    profile: TimingProfile,
+}
+
+impl SyntheticInput {
+    #[must_use]
+    pub fn new(profile: TimingProfile) -> Self {
+        Self { profile }
+    }
+
+    async fn generate(self, sink: Sink, cancel: CancellationToken) -> Result<(), String> {
+        debug!("Generating a {:?} test pattern", self.profile);
+        let filter = Arc::new(Mutex::new(SourceFilter::new(
+            Vec::new(),
+            false,
+            None,
+            Duration::MAX,
+        )));
+        let (mut video, mut audio) = sink.decoders(&filter);
+        let src = SocketAddr::from((Ipv4Addr::LOCALHOST, 0));
+        let mut tone = Tone::new(TONE, self.profile.sample_rate());
+        let audio_period = Duration::from_secs_f64(
+            f64::from(u32::try_from(SAMPLES_PER_PACKET).unwrap_or_default())
+                / self.profile.sample_rate(),
+        );
+
+        let start = Instant::now();
+        let (mut next_frame, mut next_audio) = (start, start);
+        let (mut frame, mut video_seq, mut audio_seq) = (0u16, 0u16, 0u16);
+        loop {
+            let due = if audio.is_some() {
+                next_frame.min(next_audio)
+            } else {
+                next_frame
+            };
+            tokio::select! {
+                () = cancel.cancelled() => return Ok(()),
+                () = tokio::time::sleep_until(due.into()) => {}
+            }
+
+            if due == next_frame {
+                let pixels = test_pattern(frame, self.profile.visible_lines());
+                for packet in video_packets(&pixels, frame, &mut video_seq) {
+                    if let Some(frame) = video.datagram(next_frame, src, &packet)
+                        && sink.video_tx.send(frame).await.is_err()
+                    {
+                        debug!("Receiver dropped");
+                    }
+                }
+                frame = frame.wrapping_add(1);
+                next_frame += self.profile.frame_period();
+            } else if let Some(audio) = &mut audio {
+                audio.datagram(
+                    next_audio,
+                    src,
+                    &audio_packet(audio_seq, &tone.next_packet()),
+                );
+                audio_seq = audio_seq.wrapping_add(1);
+                next_audio += audio_period;
+            }
+        }
+    }
+}
+
+impl Input for SyntheticInput {
+    fn run(self: Box<Self>, sink: Sink, cancel: CancellationToken) -> InputFuture {
+        Box::pin(self.generate(sink, cancel))
+    }
+}
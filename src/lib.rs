@@ -1,14 +1,27 @@
 pub mod args;
 pub mod audio;
+pub mod capture;
+pub mod codec;
 pub mod constants;
+pub mod harness;
+pub mod input;
 pub mod network;
+pub mod replay;
 pub mod ringbuffer;
+pub mod sim;
+pub mod stats;
+pub mod timing;
 pub mod video;
 
-pub use audio::{AudioBuffer, init_audio};
+pub use audio::{AudioBuffer, Resampler, SampleQueue, WavRecorder, WavRecording, init_audio};
+pub use capture::{Recorder, Recording};
 pub use constants::{COLORS, HEIGHT, WIDTH, colors_to_u32};
-pub use network::{NetworkConfig, network_tasks};
+pub use input::{Input, Sink, SyntheticInput};
+pub use network::{Frame, FrameAssembler, NetworkConfig, NetworkInput, network_tasks};
+pub use replay::{ReplayConfig, ReplayInput, Speed, replay_tasks};
 pub use ringbuffer::RingBuffer;
+pub use stats::{Statistics, Stats, StatsSnapshot, StreamSnapshot, StreamStats};
+pub use timing::{Timing, TimingDetector, TimingProfile};
 pub use video::{Window, run_window};
 
 use std::sync::LazyLock;
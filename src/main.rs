@@ -1,11 +1,19 @@
 use clap::Parser;
+use cpal::Stream;
 use std::{
+    path::Path,
     sync::{Arc, Mutex},
     thread,
+    time::Duration,
 };
 use tokio::sync::mpsc::{self};
 
-use lib::{CANCEL_TOKEN, RingBuffer, args::Args, video::Window};
+use lib::audio::{AudioOutput, WavFormat};
+use lib::{
+    AudioBuffer, CANCEL_TOKEN, Frame, Input, NetworkInput, Recorder, ReplayConfig, ReplayInput,
+    SampleQueue, Sink, Statistics, SyntheticInput, Timing, TimingDetector, WavRecorder,
+    WavRecording, args::Args, video::Window,
+};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt()
@@ -13,6 +21,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     let args = Args::parse();
+    if args.list_audio_devices {
+        print_audio_devices();
+        return Ok(());
+    }
     let (width, height) = args.dimensions;
     if !args.palette.is_empty() && args.palette.len() != 16 {
         eprintln!(
@@ -22,25 +34,57 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
 
+    // Only what is received from the network can be recorded
+    let live = args.replay.is_none() && args.synthetic.is_none();
+    let network_config = args.network_config();
+    let audio_output = args.audio_output();
     let palette = (!args.palette.is_empty()).then_some(args.palette);
 
+    let timing = Arc::new(Mutex::new(TimingDetector::new()));
+    let stats = Arc::new(Mutex::new(Statistics::new()));
+    let recording = Arc::new(Mutex::new(Recorder::new()));
+    if let Some(path) = &args.record
+        && let Err(e) = recording
+            .lock()
+            .expect("Unable to acquire lock on recording")
+            .start(path)
+    {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+
+    let wav = args
+        .record_wav
+        .as_deref()
+        .map(|path| start_wav(path, args.wav_format));
+
     let (audio_buffer, _stream) = if args.mute {
         (None, None)
     } else {
-        let buffer = Arc::new(Mutex::new(RingBuffer::new(48_000, 12_000)));
-        let stream = lib::init_audio(&buffer.clone());
-        (Some(buffer), Some(stream))
+        start_audio(Duration::from_millis(args.latency), &timing, &audio_output)
     };
 
     // Create channel for video buffer updates
-    let (video_tx, mut video_rx) = mpsc::channel::<Vec<u8>>(20);
+    let (video_tx, mut video_rx) = mpsc::channel::<Frame>(20);
 
     // Spawn concurrent tasks
-    let network_config = lib::NetworkConfig {
-        video_maddr: args.video_maddr,
-        audio_maddr: args.audio_maddr,
-        video_port: args.video_port,
-        audio_port: args.audio_port,
+    let input: Box<dyn Input> = if let Some(profile) = args.synthetic {
+        Box::new(SyntheticInput::new(profile))
+    } else if let Some(path) = args.replay {
+        let replay = ReplayConfig {
+            path,
+            speed: args.speed,
+        };
+        Box::new(ReplayInput::new(replay, network_config))
+    } else {
+        Box::new(NetworkInput::new(network_config, recording.clone()))
+    };
+    let sink = Sink {
+        video_tx,
+        audio_buffer,
+        wav: wav.clone(),
+        timing: timing.clone(),
+        stats: stats.clone(),
     };
     thread::spawn(move || {
         let rt = match tokio::runtime::Runtime::new() {
@@ -50,15 +94,85 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 return;
             }
         };
-        rt.block_on(async {
-            if let Err(e) = lib::network_tasks(network_config, video_tx, audio_buffer).await {
-                eprintln!("Network task error: {e}");
-            }
-        });
+        if let Err(e) = rt.block_on(input.run(sink, CANCEL_TOKEN.clone())) {
+            eprintln!("Input error: {e}");
+        }
     });
 
-    lib::run_window(&Window { width, height }, palette.as_deref(), &mut video_rx)?;
+    lib::run_window(
+        &Window {
+            width,
+            height,
+            show_loss: args.show_loss,
+            osd: args.osd,
+        },
+        palette.as_deref(),
+        &mut video_rx,
+        &timing,
+        &stats,
+        live.then_some(&recording),
+    )?;
 
     CANCEL_TOKEN.cancel();
+    if let Some(path) = recording
+        .lock()
+        .expect("Unable to acquire lock on recording")
+        .stop()?
+    {
+        println!("Recorded to {}", path.display());
+    }
+    if let Some(wav) = wav {
+        for path in wav.lock().expect("Unable to acquire lock on wav").stop()? {
+            println!("Recorded audio to {}", path.display());
+        }
+    }
     Ok(())
 }
+
+// Starts playing the audio, continues without if the device can't be opened
+fn start_audio(
+    latency: Duration,
+    timing: &Timing,
+    output: &AudioOutput,
+) -> (Option<AudioBuffer>, Option<Stream>) {
+    // PAL is assumed until the machine is detected
+    let rate = timing
+        .lock()
+        .expect("Unable to acquire lock on timing")
+        .stream_rate();
+    let target = lib::audio::latency_samples(latency, rate);
+    let buffer = Arc::new(SampleQueue::new(48_000.max(4 * target), target));
+    match lib::init_audio(&buffer, latency, output) {
+        Ok(stream) => (Some(buffer), Some(stream)),
+        Err(e) => {
+            eprintln!("Warning: {e}, continuing without audio");
+            (None, None)
+        }
+    }
+}
+
+// Starts recording the audio to a WAV file, exits if it can't be created
+fn start_wav(path: &Path, format: WavFormat) -> WavRecording {
+    let mut recorder = WavRecorder::new();
+    if let Err(e) = recorder.start(path, format) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    Arc::new(Mutex::new(recorder))
+}
+
+fn print_audio_devices() {
+    let default = |is_default: bool| if is_default { " (default)" } else { "" };
+    for host in lib::audio::output_devices() {
+        println!("{}{}", host.host, default(host.default));
+        if host.devices.is_empty() {
+            println!("  No output devices");
+        }
+        for (index, name) in host.devices.iter().enumerate() {
+            println!(
+                "  {index}: {name}{}",
+                default(host.default_device == Some(index))
+            );
+        }
+    }
+}
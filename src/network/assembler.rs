@@ -0,0 +1,179 @@
+use super::VideoPacket;
+use crate::constants::MAX_HEIGHT;
+
+const MAX_LATE_FRAMES: u16 = 3; // Packets of frames further behind are from a restarted sender
+
+/// A complete video frame of packed pixels
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub bits: u8,             // Bits per pixel
+    pub pixels: Vec<u8>,      // `stride()` bytes per line
+    pub concealed: Vec<bool>, // Per line, `true` if the line was filled from the previous frame
+}
+
+impl Frame {
+    /// Number of bytes per line
+    #[must_use]
+    pub fn stride(&self) -> usize {
+        self.width * usize::from(self.bits) / 8
+    }
+}
+
+/// Reassembles video packets into complete frames.
+///
+/// Every packet is placed at the row given by its line number, so reordered packets end up
+/// in the right place. Packets belonging to a frame that has already been handed off are
+/// dropped, which keeps packets from two frames from being mixed. A frame number further
+/// behind than a few frames means the sender restarted, so assembly starts over with it.
+/// Lines that never arrived keep the content of the previous frame.
+///
+/// The frame geometry follows the packet headers: the width and depth come from the `width`
+/// and `bits` fields and the height from the end-of-frame packet, or from the highest line
+/// seen when that packet got lost.
+pub struct FrameAssembler {
+    current: Option<u16>, // Frame number being assembled
+    complete: bool,       // End-of-frame marker of `current` has been seen
+    synced: bool,         // Seen at least one end-of-frame marker
+    width: usize,
+    bits: u8,
+    height: usize,      // Highest line seen in `current`
+    last_height: usize, // Height of the previous frame
+    buffer: Vec<u8>,
+    received: Vec<bool>, // Per line, `true` if the line arrived for `current`
+}
+
+impl FrameAssembler {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            current: None,
+            complete: false,
+            synced: false,
+            width: 0,
+            bits: 0,
+            height: 0,
+            last_height: 0,
+            buffer: Vec::new(),
+            received: Vec::new(),
+        }
+    }
+
+    /// Adds a packet to the frame being assembled.
+    ///
+    /// Returns the finished frame once the end-of-frame marker is seen, or when a packet of a
+    /// newer frame arrives before the current frame was completed.
+    ///
+    /// # Panics
+    /// Panics if the lines of `packet` go past `MAX_HEIGHT`, which `decode_video` rejects
+    pub fn push(&mut self, packet: &VideoPacket) -> Option<Frame> {
+        let mut finished = None;
+        let frame = packet.frame;
+
+        if usize::from(packet.width) != self.width || packet.bits != self.bits {
+            // The previous content is of no use with a different geometry
+            self.width = usize::from(packet.width);
+            self.bits = packet.bits;
+            self.current = None;
+            self.last_height = 0;
+            self.buffer.clear();
+            self.received.clear();
+        }
+        let stride = self.stride();
+        if stride == 0 {
+            return None;
+        }
+
+        match self.current {
+            Some(current) if current == frame => {
+                if self.complete {
+                    // Late packet of a frame that was already handed off
+                    return None;
+                }
+            }
+            Some(current) if is_older(frame, current) => return None,
+            Some(_) => {
+                // The end-of-frame packet of the current frame got lost, or the sender restarted
+                if !self.complete && self.synced {
+                    finished = Some(self.take());
+                }
+                self.start(frame);
+            }
+            None => self.start(frame),
+        }
+
+        let first = usize::from(packet.line_index());
+        let end = first + (packet.data.len() / stride).min(usize::from(packet.lpp));
+        assert!(
+            end <= MAX_HEIGHT,
+            "Video packet lines {first}..{end} out of range"
+        );
+        if end > self.received.len() {
+            self.buffer.resize(end * stride, 0);
+            self.received.resize(end, false);
+        }
+        self.buffer[first * stride..end * stride]
+            .copy_from_slice(&packet.data[..(end - first) * stride]);
+        self.received[first..end].fill(true);
+        self.height = self.height.max(end);
+
+        if packet.is_last_line() {
+            self.complete = true;
+            self.height = end;
+            if self.synced {
+                finished = Some(self.take());
+            } else {
+                // Skip the first frame, we most likely joined halfway
+                self.synced = true;
+                self.last_height = self.height;
+            }
+        }
+        finished
+    }
+
+    fn stride(&self) -> usize {
+        self.width * usize::from(self.bits) / 8
+    }
+
+    fn start(&mut self, frame: u16) {
+        self.current = Some(frame);
+        self.complete = false;
+        self.height = 0;
+        self.received.fill(false);
+    }
+
+    // The buffer is kept, so missing lines of the next frame still show this frame
+    fn take(&mut self) -> Frame {
+        let height = if self.complete {
+            self.height
+        } else {
+            self.height.max(self.last_height)
+        };
+        self.last_height = height;
+
+        let stride = self.stride();
+        if height > self.received.len() {
+            self.buffer.resize(height * stride, 0);
+            self.received.resize(height, false);
+        }
+        Frame {
+            width: self.width,
+            height,
+            bits: self.bits,
+            pixels: self.buffer[..height * stride].to_vec(),
+            concealed: self.received[..height].iter().map(|&r| !r).collect(),
+        }
+    }
+}
+
+impl Default for FrameAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Frame numbers wrap around, only the last few frames before `current` are considered older
+fn is_older(frame: u16, current: u16) -> bool {
+    (1..=MAX_LATE_FRAMES).contains(&current.wrapping_sub(frame))
+}
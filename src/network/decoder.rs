@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::MutexGuard;
+use std::time::Instant;
+use tracing::debug;
+
+use super::{Filter, Frame, FrameAssembler, Verdict, decode_audio, decode_video};
+use crate::audio::Concealer;
+use crate::{AudioBuffer, RingBuffer, Statistics, Stats, Timing, WavRecording};
+
+const SOURCE_BUFFER_SIZE: usize = 12_000; // Samples kept of a source that is not played
+
+/// Turns video datagrams into frames of the selected source, fed by a socket or a replay
+pub struct VideoDecoder {
+    filter: Filter,
+    timing: Timing,
+    stats: Stats,
+    assemblers: HashMap<IpAddr, FrameAssembler>, // Every source is assembled on its own
+    last_accepted: Option<Instant>,              // Arrival of the last packet of the source shown
+}
+
+impl VideoDecoder {
+    #[must_use]
+    pub fn new(filter: Filter, timing: Timing, stats: Stats) -> Self {
+        Self {
+            filter,
+            timing,
+            stats,
+            assemblers: HashMap::new(),
+            last_accepted: None,
+        }
+    }
+
+    /// Processes a `datagram` from `src` received at `now` and returns the frame it completed,
+    /// if any
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `filter`, `timing` or `stats` lock
+    pub fn datagram(&mut self, now: Instant, src: SocketAddr, datagram: &[u8]) -> Option<Frame> {
+        let (verdict, key) = check_source(&self.filter, src, now);
+        if verdict == Verdict::Rejected {
+            return None;
+        }
+        let packet = match decode_video(datagram) {
+            Ok(p) => p,
+            Err(e) => {
+                let count = lock_stats(&self.stats).video.malformed(&e);
+                debug!("Dropped video packet from {src}: {e} ({count} times)");
+                return None;
+            }
+        };
+
+        // Assembled even if not shown, so the frames of the sources can't interleave
+        let frame = self.assemblers.entry(key).or_default().push(&packet);
+        if verdict == Verdict::Other {
+            return None;
+        }
+        self.last_accepted = Some(now);
+        {
+            let mut stats = lock_stats(&self.stats);
+            stats.video.packet(now, packet.seq);
+            stats.video.source(src);
+        }
+
+        let frame = frame?;
+        lock_stats(&self.stats).video.frame(now);
+        self.timing
+            .lock()
+            .expect("Unable to acquire lock on timing")
+            .frame(now, frame.height);
+        Some(frame)
+    }
+
+    /// Registers that the stream went silent and returns whether it had a signal until now
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `stats` lock
+    pub fn signal_lost(&mut self) -> bool {
+        // The senders may have restarted, start over with the next frame
+        self.assemblers.clear();
+        lock_stats(&self.stats).video.signal_lost()
+    }
+
+    /// Arrival of the last valid packet of the source that is shown, packets of rejected or
+    /// other sources don't count
+    #[must_use]
+    pub fn last_accepted(&self) -> Option<Instant> {
+        self.last_accepted
+    }
+}
+
+// Audio of a source that is not played is kept in its own buffer, so it can be played at once
+// when the source gets selected
+struct AudioSource {
+    concealer: Concealer,
+    buffer: RingBuffer<f32>,
+}
+
+impl Default for AudioSource {
+    fn default() -> Self {
+        Self {
+            concealer: Concealer::new(),
+            buffer: RingBuffer::new(SOURCE_BUFFER_SIZE, 0),
+        }
+    }
+}
+
+/// Turns audio datagrams into samples of the selected source in the audio buffer and the WAV
+/// recording, fed by a socket or a replay
+pub struct AudioDecoder {
+    filter: Filter,
+    audio_buffer: Option<AudioBuffer>, // If muted `audio_buffer` is `None`
+    wav: Option<WavRecording>,         // If not recording to a WAV file `wav` is `None`
+    timing: Timing,
+    stats: Stats,
+    sources: HashMap<IpAddr, AudioSource>,
+    played: Option<IpAddr>, // Source whose samples are in `audio_buffer`
+    last_accepted: Option<Instant>, // Arrival of the last packet of the source played
+}
+
+impl AudioDecoder {
+    #[must_use]
+    pub fn new(
+        filter: Filter,
+        audio_buffer: Option<AudioBuffer>,
+        wav: Option<WavRecording>,
+        timing: Timing,
+        stats: Stats,
+    ) -> Self {
+        Self {
+            filter,
+            audio_buffer,
+            wav,
+            timing,
+            stats,
+            sources: HashMap::new(),
+            played: None,
+            last_accepted: None,
+        }
+    }
+
+    /// Processes a `datagram` from `src` received at `now`
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `filter`, `timing`, `stats` or `wav` lock
+    pub fn datagram(&mut self, now: Instant, src: SocketAddr, datagram: &[u8]) {
+        let (verdict, key) = check_source(&self.filter, src, now);
+        if verdict == Verdict::Rejected {
+            return;
+        }
+        let audio_stream = match decode_audio(datagram) {
+            Ok(a) => a,
+            Err(e) => {
+                let count = lock_stats(&self.stats).audio.malformed(&e);
+                debug!("Dropped audio packet from {src}: {e} ({count} times)");
+                return;
+            }
+        };
+        let seq = audio_stream.seq();
+
+        let fresh = !self.sources.contains_key(&key);
+        let source = self.sources.entry(key).or_default();
+        let (lost, late) = (source.concealer.lost(), source.concealer.late());
+        let restarts = source.concealer.restarts();
+        let samples = source.concealer.push(seq, &audio_stream.samples());
+        if verdict == Verdict::Other {
+            for sample in samples {
+                source.buffer.push(sample);
+            }
+            return;
+        }
+        self.last_accepted = Some(now);
+        if source.concealer.lost() > lost {
+            debug!(
+                "Concealed {} lost audio packets before {seq}",
+                source.concealer.lost() - lost
+            );
+        }
+        if source.concealer.late() > late {
+            debug!("Dropped audio packet {seq}, it arrived too late");
+        }
+        {
+            let mut stats = lock_stats(&self.stats);
+            stats.audio.packet(now, seq);
+            stats.audio.source(src);
+        }
+
+        let (profile, rate) = {
+            let mut timing = self
+                .timing
+                .lock()
+                .expect("Unable to acquire lock on timing");
+            timing.audio_packet(now, seq);
+            (timing.profile(), timing.stream_rate())
+        };
+
+        if let Some(wav) = &self.wav {
+            // Fill the time the stream was gone once it is back
+            let resync = fresh || source.concealer.restarts() > restarts;
+            wav.lock()
+                .expect("Unable to acquire lock on wav")
+                .write(now, profile, &samples, resync);
+        }
+
+        let Some(buffer) = &self.audio_buffer else {
+            return;
+        };
+        buffer.set_sample_rate(rate);
+        if self.played != Some(key) {
+            // Continue with the samples that arrived while the source was not played
+            buffer.clear();
+            buffer.push_slice(&source.buffer.drain().collect::<Vec<_>>());
+            self.played = Some(key);
+        }
+        buffer.push_slice(&samples);
+        lock_stats(&self.stats).audio_buffer(buffer.len(), buffer.underruns(), buffer.overruns());
+    }
+
+    /// Registers that the stream went silent and returns whether it had a signal until now
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `stats` lock
+    pub fn signal_lost(&mut self) -> bool {
+        // Don't fill the silence with a gap once packets come back
+        self.sources.clear();
+        lock_stats(&self.stats).audio.signal_lost()
+    }
+
+    /// Arrival of the last valid packet of the source that is played, packets of rejected or
+    /// other sources don't count
+    #[must_use]
+    pub fn last_accepted(&self) -> Option<Instant> {
+        self.last_accepted
+    }
+}
+
+// Classifies a datagram and returns the key of its per-source state
+fn check_source(filter: &Filter, src: SocketAddr, now: Instant) -> (Verdict, IpAddr) {
+    let mut filter = filter
+        .lock()
+        .expect("Unable to acquire lock on source filter");
+    (filter.check(src.ip(), now), filter.key(src.ip()))
+}
+
+fn lock_stats(stats: &Stats) -> MutexGuard<'_, Statistics> {
+    stats.lock().expect("Unable to acquire lock on stats")
+}
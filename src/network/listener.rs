@@ -0,0 +1,92 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use super::Membership;
+use crate::capture::{Recording, StreamKind};
+
+/// Outcome of waiting for a datagram
+pub enum Received {
+    Datagram(usize, SocketAddr, Instant), // Length, sender and time of arrival
+    Timeout,
+    Cancelled,
+}
+
+/// Socket of a single stream with its multicast membership
+pub struct Listener {
+    socket: UdpSocket,
+    membership: Membership,
+    timeout: Duration, // Silence after which `Received::Timeout` is returned
+    kind: StreamKind,
+    recording: Recording, // Every datagram is passed to the recorder
+    cancel: CancellationToken,
+}
+
+impl Listener {
+    #[must_use]
+    pub fn new(
+        socket: UdpSocket,
+        membership: Membership,
+        timeout: Duration,
+        kind: StreamKind,
+        recording: Recording,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            socket,
+            membership,
+            timeout,
+            kind,
+            recording,
+            cancel,
+        }
+    }
+
+    /// Waits at most the timeout for the next datagram and records it
+    ///
+    /// # Errors
+    /// Returns an error if receiving from the socket fails
+    ///
+    /// # Panics
+    /// Panics if unable to acquire a `recording` lock
+    pub async fn receive(&self, buf: &mut [u8]) -> io::Result<Received> {
+        let (len, src) = tokio::select! {
+            () = self.cancel.cancelled() => return Ok(Received::Cancelled),
+            received = self.socket.recv_from(buf) => received?,
+            () = tokio::time::sleep(self.timeout) => return Ok(Received::Timeout),
+        };
+        let now = Instant::now();
+        self.recording
+            .lock()
+            .expect("Unable to acquire lock on recording")
+            .record(now, self.kind, src, &buf[..len]);
+        Ok(Received::Datagram(len, src, now))
+    }
+
+    /// Leaves and joins the multicast group again
+    ///
+    /// IGMP snooping switches sometimes forget our membership, so it is renewed while the stream
+    /// is silent. Failures are only logged, the next timeout tries again.
+    pub fn rejoin(&self) {
+        _ = self.membership.leave(&self.socket);
+        if let Err(e) = self.membership.join(&self.socket) {
+            debug!(
+                "Unable to rejoin multicast group {}: {e}",
+                self.membership.group()
+            );
+        }
+    }
+
+    #[must_use]
+    pub fn group(&self) -> Ipv4Addr {
+        self.membership.group()
+    }
+
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
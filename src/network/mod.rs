@@ -1,58 +1,161 @@
+mod assembler;
+mod decoder;
+mod listener;
 mod protocol;
+mod socket;
+mod source;
 
-use std::net::Ipv4Addr;
-use tokio::net::UdpSocket;
+pub use crate::codec::{
+    AudioStream, Encoding, PacketError, VideoPacket, decode_audio, decode_rle, decode_video,
+};
+pub use assembler::{Frame, FrameAssembler};
+pub use decoder::{AudioDecoder, VideoDecoder};
+pub use listener::{Listener, Received};
+pub use protocol::{DropCounts, Watchdog};
+pub use socket::{Interface, Membership, bind_multicast};
+pub use source::{SourceFilter, Verdict};
+
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
-use crate::{AudioBuffer, CANCEL_TOKEN};
+use crate::capture::{Recording, StreamKind};
+use crate::input::{Input, InputFuture, Sink};
+use crate::{AudioBuffer, CANCEL_TOKEN, Stats, Timing};
+
+pub type Filter = Arc<Mutex<SourceFilter>>;
 
 pub struct NetworkConfig {
     pub video_maddr: Ipv4Addr,
     pub audio_maddr: Ipv4Addr,
     pub video_port: u16,
     pub audio_port: u16,
+    pub timeout: Duration, // Silence after which a stream is considered lost
+    pub interface: Option<Interface>, // Interface for the groups, `None` lets the OS pick
+    pub recv_buffer: usize, // Requested kernel receive buffer size in bytes
+    pub allowed_sources: Vec<IpAddr>, // Senders to accept, any sender if empty
+    pub demux: bool,       // Split the streams by sender and show only one
+    pub source: Option<IpAddr>, // Sender to show, implies `demux`
+}
+
+impl NetworkConfig {
+    /// Source filter shared by the video and audio decoders
+    #[must_use]
+    pub fn filter(&self) -> Filter {
+        Arc::new(Mutex::new(SourceFilter::new(
+            self.allowed_sources.clone(),
+            self.demux,
+            self.source,
+            self.timeout,
+        )))
+    }
+}
+
+/// Receives the multicast streams of an Ultimate
+pub struct NetworkInput {
+    config: NetworkConfig,
+    recording: Recording, // Every received datagram is passed to the recorder
 }
 
+impl NetworkInput {
+    #[must_use]
+    pub fn new(config: NetworkConfig, recording: Recording) -> Self {
+        Self { config, recording }
+    }
+
+    /// Binds the sockets and joins the groups, the returned future receives until `cancel`
+    /// is cancelled
+    ///
+    /// # Errors
+    /// Returns an error if the interface is unknown or unable to bind to socket
+    pub fn listen(self, sink: Sink, cancel: CancellationToken) -> Result<InputFuture, String> {
+        debug!("Setting up network tasks");
+        let Self { config, recording } = self;
+        let filter = config.filter();
+        let (video_decoder, audio_decoder) = sink.decoders(&filter);
+
+        let video_membership = Membership::new(config.video_maddr, config.interface.as_ref())?;
+        let video_socket =
+            bind_multicast(&video_membership, config.video_port, config.recv_buffer)?;
+        let video_listener = Listener::new(
+            video_socket,
+            video_membership,
+            config.timeout,
+            StreamKind::Video,
+            recording.clone(),
+            cancel.clone(),
+        );
+
+        let audio_listener = if audio_decoder.is_some() {
+            let audio_membership = Membership::new(config.audio_maddr, config.interface.as_ref())?;
+            let audio_socket =
+                bind_multicast(&audio_membership, config.audio_port, config.recv_buffer)?;
+            Some(Listener::new(
+                audio_socket,
+                audio_membership,
+                config.timeout,
+                StreamKind::Audio,
+                recording,
+                cancel.clone(),
+            ))
+        } else {
+            None
+        };
+
+        let video_tx = sink.video_tx;
+        Ok(Box::pin(async move {
+            let video_task = tokio::spawn(async move {
+                protocol::handle_video(video_listener, video_decoder, video_tx).await
+            });
+            let audio_task =
+                if let (Some(listener), Some(decoder)) = (audio_listener, audio_decoder) {
+                    tokio::spawn(async move { protocol::handle_audio(listener, decoder).await })
+                } else {
+                    // Audio is muted
+                    tokio::spawn(async move {
+                        cancel.cancelled().await;
+                        Ok(())
+                    })
+                };
+
+            // Wait for both tasks
+            _ = tokio::try_join!(video_task, audio_task)
+                .map_err(|e| format!("Task join error: {e}"))?;
+
+            Ok(())
+        }))
+    }
+}
+
+impl Input for NetworkInput {
+    fn run(self: Box<Self>, sink: Sink, cancel: CancellationToken) -> InputFuture {
+        Box::pin(async move { self.listen(sink, cancel)?.await })
+    }
+}
+
+/// Receives the network streams until `CANCEL_TOKEN` is cancelled
+///
 /// # Errors
-/// Returns an error if unable to bind to socket
+/// Returns an error if the interface is unknown or unable to bind to socket
 pub async fn network_tasks(
     config: NetworkConfig,
-    video_tx: Sender<Vec<u8>>,
+    video_tx: Sender<Frame>,
     audio_buffer: Option<AudioBuffer>, // If muted `audio_buffer` is `None`
+    timing: Timing,
+    stats: Stats,
+    recording: Recording,
 ) -> Result<(), String> {
-    debug!("Setting up network tasks");
-    let video_maddr = config.video_maddr;
-    let audio_maddr = config.audio_maddr;
-    let video_port = config.video_port;
-    let audio_port = config.audio_port;
-    let video_socket = UdpSocket::bind(format!("0.0.0.0:{video_port}"))
-        .await
-        .map_err(|e| e.to_string())?;
-    video_socket
-        .join_multicast_v4(video_maddr, Ipv4Addr::UNSPECIFIED)
-        .map_err(|e| e.to_string())?;
-    let video_task =
-        tokio::spawn(async move { protocol::handle_video(video_socket, video_tx).await });
-
-    let audio_task = if let Some(audio_buffer) = audio_buffer {
-        let audio_socket = UdpSocket::bind(format!("0.0.0.0:{audio_port}"))
-            .await
-            .map_err(|e| e.to_string())?;
-        audio_socket
-            .join_multicast_v4(audio_maddr, Ipv4Addr::UNSPECIFIED)
-            .map_err(|e| e.to_string())?;
-        tokio::spawn(async move { protocol::handle_audio(audio_socket, audio_buffer).await })
-    } else {
-        // Audio is muted
-        tokio::spawn(async move {
-            CANCEL_TOKEN.cancelled().await;
-            Ok(())
-        })
+    let sink = Sink {
+        video_tx,
+        audio_buffer,
+        wav: None,
+        timing,
+        stats,
     };
-
-    // Wait for both tasks
-    _ = tokio::try_join!(video_task, audio_task).map_err(|e| format!("Task join error: {e}"))?;
-
-    Ok(())
+    NetworkInput::new(config, recording)
+        .listen(sink, CANCEL_TOKEN.clone())?
+        .await
 }
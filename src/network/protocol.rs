@@ -1,121 +1,141 @@
+use std::collections::BTreeMap;
 use std::io;
-use tokio::net::UdpSocket;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::debug;
-use zerocopy::{FromBytes, Immutable, KnownLayout};
+use tracing::{debug, trace};
 
-use crate::AudioBuffer;
-use crate::CANCEL_TOKEN;
+use super::{AudioDecoder, Frame, Listener, Received, VideoDecoder};
+use crate::codec::PacketError;
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, FromBytes, Immutable, KnownLayout)]
-pub struct VideoStream {
-    seq: u16,        // Sequence number
-    frame: u16,      // Frame number
-    line: u16,       // Line number
-    width: u16,      // Pixels per line (always 384)
-    lpp: u8,         // Lines per packet (always 4)
-    bits: u8,        // Bit per pixel (always 4)
-    encoding: u16,   // Encoding type (0=no encoding, 1=RLE encoding)
-    data: [u8; 768], // Frame data
-}
+const RECV_BUFFER_SIZE: usize = 65_536; // Largest possible UDP datagram
+
+/// Number of dropped datagrams per reason
+#[derive(Debug, Default, Clone)]
+pub struct DropCounts(BTreeMap<&'static str, u64>);
+
+impl DropCounts {
+    /// Counts a dropped datagram and returns the number of drops for the same reason
+    pub fn count(&mut self, error: &PacketError) -> u64 {
+        let count = self.0.entry(error.reason()).or_default();
+        *count += 1;
+        *count
+    }
 
-#[repr(C)]
-#[derive(Debug, Clone, Copy, FromBytes, Immutable, KnownLayout)]
-pub struct AudioStream {
-    seq: u16,              // Sequence number
-    data: [[i16; 2]; 192], // Left channel, Right channel
+    #[must_use]
+    pub fn get(&self, reason: &str) -> u64 {
+        self.0.get(reason).copied().unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.0.values().sum()
+    }
 }
 
-pub async fn handle_video(socket: UdpSocket, sender: mpsc::Sender<Vec<u8>>) -> io::Result<()> {
-    debug!("Starting video handler");
-    let mut buf = vec![0u8; 780];
-    let mut first_run = true;
+/// Times the silence of the selected source, datagrams of other senders don't reset it
+pub struct Watchdog {
+    timeout: Duration,
+    since: Instant, // Last accepted packet, or the last time the silence was handled
+}
 
-    while !CANCEL_TOKEN.is_cancelled() {
-        let mut frame_data = Vec::new();
+impl Watchdog {
+    /// Starts timing at `now`
+    #[must_use]
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self {
+            timeout,
+            since: now,
+        }
+    }
 
-        loop {
-            let (len, src) = socket.recv_from(&mut buf).await?;
-            debug!("Video: {} bytes from {}", len, src);
-            let video_stream = match VideoStream::read_from_bytes(&buf) {
-                Ok(v) => v,
-                Err(e) => {
-                    debug!("Failed to parse video stream: {e:?}");
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!("Invalid video packet: {e:?}"),
-                    ));
-                }
-            };
-            if video_stream.line & 0x8000 != 0 {
-                first_run = false;
-            }
+    /// Returns whether the selected source, whose last packet arrived at `last_accepted`, has
+    /// been silent for the timeout at `now`. Once expired it is timed again from `now`.
+    pub fn expired(&mut self, now: Instant, last_accepted: Option<Instant>) -> bool {
+        self.since = self.since.max(last_accepted.unwrap_or(self.since));
+        if now.saturating_duration_since(self.since) < self.timeout {
+            return false;
+        }
+        self.since = now;
+        true
+    }
+}
 
-            if !first_run {
-                frame_data.extend_from_slice(&video_stream.data);
+/// Receives video packets from `listener` and sends the completed frames of the selected source
+/// to `sender`
+///
+/// When no packet of the selected source arrives for the timeout of `listener` the stream is
+/// marked as lost and the group is rejoined until packets come back.
+///
+/// # Errors
+/// Returns an error if receiving from the socket fails
+pub async fn handle_video(
+    listener: Listener,
+    mut decoder: VideoDecoder,
+    sender: mpsc::Sender<Frame>,
+) -> io::Result<()> {
+    debug!("Starting video handler");
+    let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+    let mut watchdog = Watchdog::new(listener.timeout(), Instant::now());
 
-                if video_stream.line & 0x8000 != 0 {
-                    if (sender.send(frame_data).await).is_err() {
-                        debug!("Receiver dropped");
-                    }
-                    break;
+    loop {
+        let now = match listener.receive(&mut buf).await? {
+            Received::Datagram(len, src, now) => {
+                trace!("Video: {} bytes from {}", len, src);
+                if let Some(frame) = decoder.datagram(now, src, &buf[..len])
+                    && sender.send(frame).await.is_err()
+                {
+                    debug!("Receiver dropped");
                 }
-            } else if video_stream.line & 0x8000 != 0 {
-                break;
+                now
+            }
+            Received::Timeout => Instant::now(),
+            Received::Cancelled => break,
+        };
+        if watchdog.expired(now, decoder.last_accepted()) {
+            if decoder.signal_lost() {
+                debug!(
+                    "No video received for {:?}, rejoining {}",
+                    listener.timeout(),
+                    listener.group()
+                );
             }
+            listener.rejoin();
         }
     }
     Ok(())
 }
 
-pub async fn handle_audio(socket: UdpSocket, audio_buffer: AudioBuffer) -> io::Result<()> {
+/// Receives audio packets from `listener` and queues the samples of the selected source
+///
+/// When no packet of the selected source arrives for the timeout of `listener` the stream is
+/// marked as lost and the group is rejoined until packets come back.
+///
+/// # Errors
+/// Returns an error if receiving from the socket fails
+pub async fn handle_audio(listener: Listener, mut decoder: AudioDecoder) -> io::Result<()> {
     debug!("Starting audio handler");
-    let mut previous_seq: Option<u16> = None;
-    let mut buf = vec![0u8; 770];
-    while !CANCEL_TOKEN.is_cancelled() {
-        let (len, src) = socket.recv_from(&mut buf).await?;
-        debug!("Audio: {} bytes from {}", len, src);
-        // Process audio packet
-        let audio_stream = match AudioStream::read_from_bytes(&buf) {
-            Ok(a) => a,
-            Err(e) => {
-                debug!("Failed to parse audio stream: {e:?}");
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    format!("Invalid audio packet: {e:?}"),
-                ));
+    let mut buf = vec![0u8; RECV_BUFFER_SIZE];
+    let mut watchdog = Watchdog::new(listener.timeout(), Instant::now());
+
+    loop {
+        let now = match listener.receive(&mut buf).await? {
+            Received::Datagram(len, src, now) => {
+                trace!("Audio: {} bytes from {}", len, src);
+                decoder.datagram(now, src, &buf[..len]);
+                now
             }
+            Received::Timeout => Instant::now(),
+            Received::Cancelled => break,
         };
-
-        // Check for dropped packets
-        if let Some(prev) = previous_seq
-            && audio_stream.seq != prev.wrapping_add(1)
-        {
-            debug!(
-                "Dropped audio packet! Expected {}, got {}",
-                prev.wrapping_add(1),
-                audio_stream.seq,
-            );
-
-            let mut buffer = audio_buffer
-                .lock()
-                .expect("Unable to acquire lock on audio_buffer");
-            for _ in 0..384 {
-                buffer.push(0.);
+        if watchdog.expired(now, decoder.last_accepted()) {
+            if decoder.signal_lost() {
+                debug!(
+                    "No audio received for {:?}, rejoining {}",
+                    listener.timeout(),
+                    listener.group()
+                );
             }
-        }
-        previous_seq = Some(audio_stream.seq);
-
-        let mut buffer = audio_buffer
-            .lock()
-            .expect("Unable to acquire lock on audio_buffer");
-
-        for sample_pair in &audio_stream.data {
-            let left = f32::from(sample_pair[0]) / 32768.;
-            let right = f32::from(sample_pair[1]) / 32768.;
-            buffer.push(left);
-            buffer.push(right);
+            listener.rejoin();
         }
     }
     Ok(())
@@ -0,0 +1,142 @@
+use socket2::{Domain, InterfaceIndexOrAddress, Protocol, SockRef, Socket, Type};
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::str::FromStr;
+use tokio::net::UdpSocket;
+use tracing::debug;
+
+/// Network interface to join the multicast groups on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interface {
+    Address(Ipv4Addr), // An address assigned to the interface
+    Name(String),      // Name of the interface, e.g. `eth0`
+}
+
+impl FromStr for Interface {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("Interface must not be empty".to_string());
+        }
+        Ok(s.parse()
+            .map_or_else(|_| Self::Name(s.to_string()), Self::Address))
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(addr) => write!(f, "{addr}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// A multicast group and the interface it is joined on
+#[derive(Debug, Clone, Copy)]
+pub struct Membership {
+    group: Ipv4Addr,
+    interface: InterfaceIndexOrAddress,
+}
+
+impl Membership {
+    /// Without an `interface` the operating system picks one
+    ///
+    /// # Errors
+    /// Returns an error if there is no interface with the given name
+    pub fn new(group: Ipv4Addr, interface: Option<&Interface>) -> Result<Self, String> {
+        let interface = match interface {
+            None => InterfaceIndexOrAddress::Address(Ipv4Addr::UNSPECIFIED),
+            Some(Interface::Address(addr)) => InterfaceIndexOrAddress::Address(*addr),
+            Some(Interface::Name(name)) => InterfaceIndexOrAddress::Index(interface_index(name)?),
+        };
+        Ok(Self { group, interface })
+    }
+
+    #[must_use]
+    pub fn group(&self) -> Ipv4Addr {
+        self.group
+    }
+
+    /// # Errors
+    /// Returns an error if the operating system refuses the membership
+    pub fn join(&self, socket: &UdpSocket) -> io::Result<()> {
+        SockRef::from(socket).join_multicast_v4_n(&self.group, &self.interface)
+    }
+
+    /// # Errors
+    /// Returns an error if the group was not joined
+    pub fn leave(&self, socket: &UdpSocket) -> io::Result<()> {
+        SockRef::from(socket).leave_multicast_v4_n(&self.group, &self.interface)
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> Result<u32, String> {
+    let c_name =
+        std::ffi::CString::new(name).map_err(|_| format!("Invalid interface name: {name}"))?;
+    // SAFETY: `c_name` is a valid NUL terminated string that outlives the call
+    let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    if index == 0 {
+        return Err(format!("Unknown network interface: {name}"));
+    }
+    Ok(index)
+}
+
+#[cfg(not(unix))]
+fn interface_index(name: &str) -> Result<u32, String> {
+    Err(format!(
+        "Interface names are not supported on this platform, use the address of {name} instead"
+    ))
+}
+
+/// Binds a UDP socket to `port` on all addresses and joins `membership`. The port can be shared
+/// with other viewers on the same host.
+///
+/// `recv_buffer` is the requested kernel receive buffer size in bytes, the operating system may
+/// limit it.
+///
+/// # Errors
+/// Returns an error if the socket can't be created, bound or can't join the multicast group
+pub fn bind_multicast(
+    membership: &Membership,
+    port: u16,
+    recv_buffer: usize,
+) -> Result<UdpSocket, String> {
+    let group = membership.group();
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .map_err(|e| format!("Unable to create socket: {e}"))?;
+    socket
+        .set_reuse_address(true)
+        .map_err(|e| format!("Unable to set SO_REUSEADDR: {e}"))?;
+    #[cfg(all(
+        unix,
+        not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+    ))]
+    socket
+        .set_reuse_port(true)
+        .map_err(|e| format!("Unable to set SO_REUSEPORT: {e}"))?;
+    socket
+        .set_recv_buffer_size(recv_buffer)
+        .map_err(|e| format!("Unable to set SO_RCVBUF: {e}"))?;
+    if let Ok(size) = socket.recv_buffer_size()
+        && size < recv_buffer
+    {
+        debug!("Receive buffer for {group} is {size} bytes, {recv_buffer} bytes requested");
+    }
+    socket
+        .set_nonblocking(true)
+        .map_err(|e| format!("Unable to set non-blocking mode: {e}"))?;
+    socket
+        .bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port).into())
+        .map_err(|e| format!("Unable to bind to port {port}: {e}"))?;
+
+    let socket = UdpSocket::from_std(socket.into()).map_err(|e| e.to_string())?;
+    membership
+        .join(&socket)
+        .map_err(|e| format!("Unable to join multicast group {group}: {e}"))?;
+    Ok(socket)
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// What to do with a datagram from a given sender
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Rejected, // Sender is not allowed
+    Selected, // Belongs to the stream that is shown
+    Other,    // Belongs to another stream, only when demultiplexing
+}
+
+/// Decides which senders are accepted and, when demultiplexing, which one is shown
+#[derive(Debug)]
+pub struct SourceFilter {
+    allowed: Vec<IpAddr>,       // Accepted senders, any sender if empty
+    demux: bool,                // Split the packets into one stream per sender
+    chosen: Option<IpAddr>,     // Sender picked by the user
+    selected: Option<IpAddr>,   // Sender that is shown
+    last_seen: Option<Instant>, // Last packet of the selected sender
+    timeout: Duration,          // Silence after which another sender is locked onto
+    rejected: HashSet<IpAddr>,  // Senders that were logged as rejected
+}
+
+impl SourceFilter {
+    /// Choosing a source implies demultiplexing
+    #[must_use]
+    pub fn new(
+        allowed: Vec<IpAddr>,
+        demux: bool,
+        chosen: Option<IpAddr>,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            allowed,
+            demux: demux || chosen.is_some(),
+            chosen,
+            selected: chosen,
+            last_seen: None,
+            timeout,
+            rejected: HashSet::new(),
+        }
+    }
+
+    /// Classifies a datagram from `src` received at `now`. Without a chosen source the first
+    /// allowed sender is locked onto, until it has been silent for the timeout.
+    pub fn check(&mut self, src: IpAddr, now: Instant) -> Verdict {
+        if !self.allowed.is_empty() && !self.allowed.contains(&src) {
+            if self.rejected.insert(src) {
+                debug!("Ignoring packets from {src}, it is not an allowed source");
+            }
+            return Verdict::Rejected;
+        }
+        if !self.demux {
+            return Verdict::Selected;
+        }
+        if let Some(chosen) = self.chosen {
+            return if src == chosen {
+                Verdict::Selected
+            } else {
+                Verdict::Other
+            };
+        }
+
+        let expired = self
+            .last_seen
+            .is_none_or(|last| now.saturating_duration_since(last) >= self.timeout);
+        if self.selected != Some(src) {
+            if !expired {
+                return Verdict::Other;
+            }
+            debug!("Locked onto source {src}");
+            self.selected = Some(src);
+        }
+        self.last_seen = Some(now);
+        Verdict::Selected
+    }
+
+    /// Key of the per-source state of `src`, all senders share one state unless demultiplexing
+    #[must_use]
+    pub fn key(&self, src: IpAddr) -> IpAddr {
+        if self.demux {
+            src
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }
+    }
+
+    /// Sender that is shown, if any
+    #[must_use]
+    pub fn selected(&self) -> Option<IpAddr> {
+        self.selected
+    }
+}
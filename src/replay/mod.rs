@@ -0,0 +1,216 @@
+mod pcap;
+mod udp;
+
+pub use pcap::{PcapPacket, PcapReader};
+pub use udp::{
+    LINKTYPE_ETHERNET, LINKTYPE_IPV4, LINKTYPE_LINUX_SLL, LINKTYPE_LINUX_SLL2, LINKTYPE_NULL,
+    LINKTYPE_RAW, UdpDatagram, udp_datagram,
+};
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::capture::{CaptureReader, MAGIC, StreamKind};
+use crate::input::{Input, InputFuture, Sink};
+use crate::network::Watchdog;
+use crate::{AudioBuffer, CANCEL_TOKEN, Frame, NetworkConfig, Stats, Timing};
+
+/// Replay speed relative to the original timing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    Factor(f64), // 2.0 plays twice as fast, 0.5 at half speed
+    Max,         // As fast as the datagrams can be decoded
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(Self::Max);
+        }
+        let factor = s
+            .strip_suffix('x')
+            .unwrap_or(s)
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid speed '{s}', expected a factor like 2 or 0.5, or max"))?;
+        if !factor.is_finite() || factor <= 0. {
+            return Err(format!("Speed must be larger than 0, got {s}"));
+        }
+        Ok(Self::Factor(factor))
+    }
+}
+
+pub struct ReplayConfig {
+    pub path: PathBuf, // pcap, pcapng or capture file
+    pub speed: Speed,
+}
+
+/// A datagram read from a capture
+#[derive(Debug, Clone)]
+pub struct Datagram {
+    pub time: Duration, // Time of arrival, relative to an arbitrary epoch
+    pub kind: StreamKind,
+    pub src: SocketAddr,
+    pub data: Vec<u8>,
+}
+
+pub type Datagrams = Box<dyn Iterator<Item = Result<Datagram, String>> + Send>;
+
+/// Opens a pcap, pcapng or capture file. Datagrams in pcap and pcapng files are assigned to a
+/// stream by their destination port, datagrams to other ports are skipped.
+///
+/// # Errors
+/// Returns an error if the file can't be opened or is not in a supported format
+pub fn open_capture(path: &Path, video_port: u16, audio_port: u16) -> Result<Datagrams, String> {
+    let file = File::open(path).map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let magic = reader
+        .fill_buf()
+        .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+
+    if magic.starts_with(&MAGIC) {
+        let records = CaptureReader::new(reader)?;
+        return Ok(Box::new(records.map(|record| {
+            let (header, data) = record?;
+            let kind = StreamKind::from_byte(header.kind)
+                .ok_or_else(|| format!("Unknown stream {} in capture file", header.kind))?;
+            Ok(Datagram {
+                time: Duration::from_micros(header.time.get()),
+                kind,
+                src: header.source(),
+                data,
+            })
+        })));
+    }
+
+    let packets = PcapReader::new(reader)?;
+    Ok(Box::new(packets.filter_map(move |packet| {
+        let packet = match packet {
+            Ok(packet) => packet,
+            Err(e) => return Some(Err(e)),
+        };
+        let datagram = udp_datagram(packet.link_type, &packet.data)?;
+        let kind = match datagram.dst.port() {
+            port if port == video_port => StreamKind::Video,
+            port if port == audio_port => StreamKind::Audio,
+            _ => return None,
+        };
+        Some(Ok(Datagram {
+            time: packet.time,
+            kind,
+            src: SocketAddr::V4(datagram.src),
+            data: datagram.payload.to_vec(),
+        }))
+    })))
+}
+
+/// Plays a capture back through the same decoders as the network streams
+///
+/// The original timing is kept, scaled by the replay speed. A gap in the selected source longer
+/// than the timeout in `config` is handled as a lost signal.
+pub struct ReplayInput {
+    replay: ReplayConfig,
+    config: NetworkConfig, // Ports, source selection and timeout as when receiving
+}
+
+impl ReplayInput {
+    #[must_use]
+    pub fn new(replay: ReplayConfig, config: NetworkConfig) -> Self {
+        Self { replay, config }
+    }
+
+    async fn replay(self, sink: Sink, cancel: CancellationToken) -> Result<(), String> {
+        let Self { replay, config } = self;
+        debug!("Replaying {}", replay.path.display());
+        let datagrams = open_capture(&replay.path, config.video_port, config.audio_port)?;
+        let (mut video, mut audio) = sink.decoders(&config.filter());
+
+        let start = Instant::now();
+        let mut first: Option<Duration> = None;
+        let mut video_watchdog = Watchdog::new(config.timeout, start);
+        let mut audio_watchdog = Watchdog::new(config.timeout, start);
+        let mut count = 0u64;
+        for datagram in datagrams {
+            let datagram = datagram?;
+            let offset = datagram
+                .time
+                .saturating_sub(*first.get_or_insert(datagram.time));
+            // Decoders see the original timing, whatever the speed
+            let now = start + offset;
+
+            let due = match replay.speed {
+                Speed::Factor(factor) => start + offset.div_f64(factor),
+                Speed::Max => Instant::now(),
+            };
+            tokio::select! {
+                () = cancel.cancelled() => return Ok(()),
+                () = tokio::time::sleep_until(due.into()) => {}
+            }
+
+            match datagram.kind {
+                StreamKind::Video => {
+                    if video_watchdog.expired(now, video.last_accepted()) {
+                        video.signal_lost();
+                    }
+                    if let Some(frame) = video.datagram(now, datagram.src, &datagram.data)
+                        && sink.video_tx.send(frame).await.is_err()
+                    {
+                        debug!("Receiver dropped");
+                    }
+                }
+                StreamKind::Audio => {
+                    if let Some(audio) = &mut audio {
+                        if audio_watchdog.expired(now, audio.last_accepted()) {
+                            audio.signal_lost();
+                        }
+                        audio.datagram(now, datagram.src, &datagram.data);
+                    }
+                }
+            }
+            count += 1;
+        }
+
+        debug!("Replayed {count} datagrams");
+        cancel.cancelled().await;
+        Ok(())
+    }
+}
+
+impl Input for ReplayInput {
+    fn run(self: Box<Self>, sink: Sink, cancel: CancellationToken) -> InputFuture {
+        Box::pin(self.replay(sink, cancel))
+    }
+}
+
+/// Replays a capture with a `ReplayInput` until `CANCEL_TOKEN` is cancelled
+///
+/// # Errors
+/// Returns an error if the capture can't be read
+pub async fn replay_tasks(
+    replay: ReplayConfig,
+    config: NetworkConfig,
+    video_tx: Sender<Frame>,
+    audio_buffer: Option<AudioBuffer>, // If muted `audio_buffer` is `None`
+    timing: Timing,
+    stats: Stats,
+) -> Result<(), String> {
+    let sink = Sink {
+        video_tx,
+        audio_buffer,
+        wav: None,
+        timing,
+        stats,
+    };
+    ReplayInput::new(replay, config)
+        .replay(sink, CANCEL_TOKEN.clone())
+        .await
+}
@@ -0,0 +1,316 @@
+use std::io::{self, Read};
+use std::time::Duration;
+use tracing::debug;
+
+const PCAP_MICROS: u32 = 0xA1B2_C3D4; // Classic pcap with microsecond timestamps
+const PCAP_NANOS: u32 = 0xA1B2_3C4D; // Classic pcap with nanosecond timestamps
+const SECTION_HEADER: u32 = 0x0A0D_0D0A; // pcapng section header block, also the file magic
+const BYTE_ORDER: u32 = 0x1A2B_3C4D; // pcapng byte order magic
+const INTERFACE_DESCRIPTION: u32 = 1;
+const ENHANCED_PACKET: u32 = 6;
+const OPTION_END: u16 = 0;
+const OPTION_TSRESOL: u16 = 9; // Timestamp resolution of an interface
+const MAX_LENGTH: usize = 16 * 1024 * 1024; // Larger records are treated as corruption
+
+/// A link layer frame from a capture file
+#[derive(Debug, Clone)]
+pub struct PcapPacket {
+    pub time: Duration, // Time of capture since the UNIX epoch
+    pub link_type: u16, // `LINKTYPE_*` value of the frame
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NgInterface {
+    link_type: u16,
+    ticks_per_second: u64, // Timestamp resolution
+}
+
+enum Format {
+    Pcap { link_type: u16, nanos: bool },
+    PcapNg { interfaces: Vec<NgInterface> },
+}
+
+/// Reads the packets of a pcap or pcapng file
+pub struct PcapReader<R: Read> {
+    inner: R,
+    big_endian: bool,
+    format: Format,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// # Errors
+    /// Returns an error if the file is not a pcap or pcapng file
+    pub fn new(mut inner: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        inner
+            .read_exact(&mut magic)
+            .map_err(|e| format!("Unable to read capture: {e}"))?;
+
+        if u32::from_le_bytes(magic) == SECTION_HEADER {
+            let mut reader = Self {
+                inner,
+                big_endian: false,
+                format: Format::PcapNg {
+                    interfaces: Vec::new(),
+                },
+            };
+            reader.section_header()?;
+            return Ok(reader);
+        }
+
+        let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MICROS, _) => (false, false),
+            (PCAP_NANOS, _) => (false, true),
+            (_, PCAP_MICROS) => (true, false),
+            (_, PCAP_NANOS) => (true, true),
+            _ => return Err("Not a pcap or pcapng file".to_string()),
+        };
+        let mut header = [0u8; 20];
+        inner
+            .read_exact(&mut header)
+            .map_err(|e| format!("Truncated pcap header: {e}"))?;
+        // The upper bits of the link type hold FCS information
+        let link_type = u32_at(&header[16..20], big_endian) & 0xFFFF;
+        Ok(Self {
+            inner,
+            big_endian,
+            format: Format::Pcap {
+                link_type: u16::try_from(link_type).unwrap_or_default(),
+                nanos,
+            },
+        })
+    }
+
+    /// Reads the next packet, `None` at the end of the file
+    ///
+    /// # Errors
+    /// Returns an error if the file is corrupt
+    pub fn next_packet(&mut self) -> Result<Option<PcapPacket>, String> {
+        match self.format {
+            Format::Pcap { link_type, nanos } => self.pcap_record(link_type, nanos),
+            Format::PcapNg { .. } => self.pcapng_packet(),
+        }
+    }
+
+    fn pcap_record(&mut self, link_type: u16, nanos: bool) -> Result<Option<PcapPacket>, String> {
+        let mut header = [0u8; 16];
+        if !self.read_record(&mut header)? {
+            return Ok(None);
+        }
+        let seconds = self.u32(&header[0..4]);
+        let fraction = self.u32(&header[4..8]);
+        let len = self.length(&header[8..12])?;
+        let mut data = vec![0u8; len];
+        if !self.read_record(&mut data)? {
+            return Ok(None);
+        }
+
+        let fraction = if nanos {
+            Duration::from_nanos(u64::from(fraction))
+        } else {
+            Duration::from_micros(u64::from(fraction))
+        };
+        Ok(Some(PcapPacket {
+            time: Duration::from_secs(u64::from(seconds)) + fraction,
+            link_type,
+            data,
+        }))
+    }
+
+    fn pcapng_packet(&mut self) -> Result<Option<PcapPacket>, String> {
+        loop {
+            let mut header = [0u8; 8];
+            if !self.read_record(&mut header)? {
+                return Ok(None);
+            }
+            let block_type = self.u32(&header[0..4]);
+            if block_type == SECTION_HEADER {
+                // A new section may change the byte order
+                self.inner_section_header(&header[4..8])?;
+                continue;
+            }
+
+            let len = self.length(&header[4..8])?;
+            if len < 12 {
+                return Err(format!("Invalid pcapng block length {len}"));
+            }
+            // Block body and the trailing copy of the length
+            let mut body = vec![0u8; len - 8];
+            if !self.read_record(&mut body)? {
+                return Ok(None);
+            }
+            let body = &body[..len - 12];
+
+            match block_type {
+                INTERFACE_DESCRIPTION => self.interface_description(body)?,
+                ENHANCED_PACKET => {
+                    if let Some(packet) = self.enhanced_packet(body)? {
+                        return Ok(Some(packet));
+                    }
+                }
+                _ => {} // Statistics, name resolution, etc.
+            }
+        }
+    }
+
+    fn section_header(&mut self) -> Result<(), String> {
+        let mut len = [0u8; 4];
+        self.inner
+            .read_exact(&mut len)
+            .map_err(|e| format!("Truncated pcapng header: {e}"))?;
+        self.inner_section_header(&len)
+    }
+
+    // Reads the rest of a section header block after its length
+    fn inner_section_header(&mut self, len: &[u8]) -> Result<(), String> {
+        let mut magic = [0u8; 4];
+        self.inner
+            .read_exact(&mut magic)
+            .map_err(|e| format!("Truncated pcapng header: {e}"))?;
+        self.big_endian = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (BYTE_ORDER, _) => false,
+            (_, BYTE_ORDER) => true,
+            _ => return Err("Invalid pcapng byte order".to_string()),
+        };
+        let len = self.length(len)?;
+        if len < 28 {
+            return Err(format!("Invalid pcapng section header length {len}"));
+        }
+        let mut rest = vec![0u8; len - 12];
+        self.inner
+            .read_exact(&mut rest)
+            .map_err(|e| format!("Truncated pcapng header: {e}"))?;
+        self.format = Format::PcapNg {
+            interfaces: Vec::new(),
+        };
+        Ok(())
+    }
+
+    fn interface_description(&mut self, body: &[u8]) -> Result<(), String> {
+        if body.len() < 8 {
+            return Err("Truncated pcapng interface description".to_string());
+        }
+        let link_type = self.u16(&body[0..2]);
+        let mut ticks_per_second = 1_000_000;
+
+        let mut options = &body[8..];
+        while options.len() >= 4 {
+            let code = self.u16(&options[0..2]);
+            let len = usize::from(self.u16(&options[2..4]));
+            let value = options.get(4..4 + len).unwrap_or_default();
+            if code == OPTION_END {
+                break;
+            }
+            if code == OPTION_TSRESOL
+                && let Some(&resolution) = value.first()
+            {
+                // The most significant bit selects a power of two instead of ten
+                let exponent = u32::from(resolution & 0x7F);
+                ticks_per_second = if resolution & 0x80 == 0 {
+                    10u64.checked_pow(exponent)
+                } else {
+                    1u64.checked_shl(exponent)
+                }
+                .ok_or_else(|| format!("Unsupported timestamp resolution {resolution:#04x}"))?;
+            }
+            options = options
+                .get(4 + len.next_multiple_of(4)..)
+                .unwrap_or_default();
+        }
+
+        if let Format::PcapNg { interfaces } = &mut self.format {
+            interfaces.push(NgInterface {
+                link_type,
+                ticks_per_second,
+            });
+        }
+        Ok(())
+    }
+
+    fn enhanced_packet(&self, body: &[u8]) -> Result<Option<PcapPacket>, String> {
+        if body.len() < 20 {
+            return Err("Truncated pcapng packet".to_string());
+        }
+        let Format::PcapNg { interfaces } = &self.format else {
+            return Ok(None);
+        };
+        let id = self.u32(&body[0..4]) as usize;
+        let Some(interface) = interfaces.get(id) else {
+            debug!("Skipped packet of unknown pcapng interface {id}");
+            return Ok(None);
+        };
+        let ticks = u64::from(self.u32(&body[4..8])) << 32 | u64::from(self.u32(&body[8..12]));
+        let len = self.u32(&body[12..16]) as usize;
+        let data = body
+            .get(20..20 + len)
+            .ok_or_else(|| "Truncated pcapng packet".to_string())?;
+
+        let tps = interface.ticks_per_second;
+        let nanos = u128::from(ticks % tps) * 1_000_000_000 / u128::from(tps);
+        Ok(Some(PcapPacket {
+            time: Duration::from_secs(ticks / tps)
+                + Duration::from_nanos(u64::try_from(nanos).unwrap_or_default()),
+            link_type: interface.link_type,
+            data: data.to_vec(),
+        }))
+    }
+
+    // Fills `buf`, returns `false` at the end of the file. A capture that ends halfway through a
+    // record was probably cut off while recording and also ends there.
+    fn read_record(&mut self, buf: &mut [u8]) -> Result<bool, String> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.inner.read(&mut buf[filled..]) {
+                Ok(0) => {
+                    if filled > 0 {
+                        debug!("Capture ends with a truncated record");
+                    }
+                    return Ok(false);
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Unable to read capture: {e}")),
+            }
+        }
+        Ok(true)
+    }
+
+    fn length(&self, bytes: &[u8]) -> Result<usize, String> {
+        let len = self.u32(bytes) as usize;
+        if len > MAX_LENGTH {
+            return Err(format!("Invalid record length {len}"));
+        }
+        Ok(len)
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        u32_at(bytes, self.big_endian)
+    }
+}
+
+fn u32_at(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<PcapPacket, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_packet().transpose()
+    }
+}
@@ -0,0 +1,90 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+pub const LINKTYPE_NULL: u16 = 0; // BSD loopback
+pub const LINKTYPE_ETHERNET: u16 = 1;
+pub const LINKTYPE_RAW: u16 = 101; // Raw IP
+pub const LINKTYPE_LINUX_SLL: u16 = 113; // `tcpdump -i any` before libpcap 1.10
+pub const LINKTYPE_IPV4: u16 = 228;
+pub const LINKTYPE_LINUX_SLL2: u16 = 276; // `tcpdump -i any`
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88A8;
+const AF_INET: u32 = 2;
+const IPPROTO_UDP: u8 = 17;
+
+/// Payload of a UDP datagram in a captured frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UdpDatagram<'a> {
+    pub src: SocketAddrV4,
+    pub dst: SocketAddrV4,
+    pub payload: &'a [u8],
+}
+
+/// Extracts the UDP datagram from a frame of `link_type`. Other protocols, IPv6 and fragmented
+/// datagrams return `None`.
+#[must_use]
+pub fn udp_datagram(link_type: u16, frame: &[u8]) -> Option<UdpDatagram<'_>> {
+    let packet = match link_type {
+        LINKTYPE_ETHERNET => ethernet(frame)?,
+        LINKTYPE_NULL => {
+            // The address family is in the byte order of the capturing host
+            let family = frame.get(..4)?;
+            let family = [family[0], family[1], family[2], family[3]];
+            if u32::from_le_bytes(family) != AF_INET && u32::from_be_bytes(family) != AF_INET {
+                return None;
+            }
+            &frame[4..]
+        }
+        LINKTYPE_RAW | LINKTYPE_IPV4 => frame,
+        LINKTYPE_LINUX_SLL if be_u16(frame, 14)? == ETHERTYPE_IPV4 => frame.get(16..)?,
+        LINKTYPE_LINUX_SLL2 if be_u16(frame, 0)? == ETHERTYPE_IPV4 => frame.get(20..)?,
+        _ => return None,
+    };
+    ipv4_udp(packet)
+}
+
+// Skips the Ethernet header and VLAN tags
+fn ethernet(frame: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    let mut ethertype = be_u16(frame, offset)?;
+    while ethertype == ETHERTYPE_VLAN || ethertype == ETHERTYPE_QINQ {
+        offset += 4;
+        ethertype = be_u16(frame, offset)?;
+    }
+    if ethertype != ETHERTYPE_IPV4 {
+        return None;
+    }
+    frame.get(offset + 2..)
+}
+
+fn ipv4_udp(packet: &[u8]) -> Option<UdpDatagram<'_>> {
+    let version = packet.first()? >> 4;
+    let header_len = usize::from(packet.first()? & 0x0F) * 4;
+    if version != 4 || header_len < 20 || packet.len() < header_len || packet[9] != IPPROTO_UDP {
+        return None;
+    }
+    // More fragments flag or a fragment offset
+    if be_u16(packet, 6)? & 0x3FFF != 0 {
+        return None;
+    }
+    let total_len = usize::from(be_u16(packet, 2)?).min(packet.len());
+    let src = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let dst = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+
+    let udp = packet.get(header_len..total_len)?;
+    let udp_len = usize::from(be_u16(udp, 4)?);
+    if udp_len < 8 {
+        return None;
+    }
+    Some(UdpDatagram {
+        src: SocketAddrV4::new(src, be_u16(udp, 0)?),
+        dst: SocketAddrV4::new(dst, be_u16(udp, 2)?),
+        payload: udp.get(8..udp_len)?,
+    })
+}
+
+fn be_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
@@ -4,6 +4,9 @@ pub struct RingBuffer<T> {
     buffer: VecDeque<T>,
     max_size: usize,
     min_fill: usize,
+    starved: bool,  // Last `pop` returned silence
+    underruns: u64, // Times playback ran dry
+    overruns: u64,  // Samples dropped because the buffer was full
 }
 
 impl<T> RingBuffer<T>
@@ -16,24 +19,40 @@ where
             buffer: VecDeque::new(),
             max_size,
             min_fill,
+            starved: true,
+            underruns: 0,
+            overruns: 0,
         }
     }
 
     pub fn push(&mut self, sample: T) {
         if self.buffer.len() >= self.max_size {
             self.buffer.pop_front();
+            self.overruns += 1;
         }
         self.buffer.push_back(sample);
     }
 
     pub fn pop(&mut self) -> T {
-        if self.buffer.len() >= self.min_fill {
-            self.buffer.pop_front().unwrap_or_default()
+        if self.buffer.len() >= self.min_fill
+            && let Some(sample) = self.buffer.pop_front()
+        {
+            self.starved = false;
+            sample
         } else {
+            if !self.starved {
+                self.starved = true;
+                self.underruns += 1;
+            }
             T::default()
         }
     }
 
+    /// Removes all samples, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.buffer.drain(..)
+    }
+
     #[must_use]
     pub fn len(&self) -> usize {
         self.buffer.len()
@@ -43,4 +62,14 @@ where
     pub fn is_empty(&self) -> bool {
         self.buffer.is_empty()
     }
+
+    #[must_use]
+    pub fn underruns(&self) -> u64 {
+        self.underruns
+    }
+
+    #[must_use]
+    pub fn overruns(&self) -> u64 {
+        self.overruns
+    }
 }
@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::constants::{COLORS, WIDTH};
+
+/// Loads a PNG image, scales it to `WIDTH` by `lines` pixels and quantizes it to the C64 palette
+///
+/// # Errors
+/// Returns an error if the file can't be read or is not a valid PNG image
+pub fn load_png(path: &Path, lines: usize) -> Result<Vec<u8>, String> {
+    let file = File::open(path).map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+    let mut buffer = vec![
+        0;
+        reader
+            .output_buffer_size()
+            .ok_or_else(|| format!("Image {} is too large", path.display()))?
+    ];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| format!("Unable to decode {}: {e}", path.display()))?;
+
+    let channels = info.color_type.samples();
+    let (width, height) = (info.width as usize, info.height as usize);
+    let rgb: Vec<[u8; 3]> = buffer[..info.line_size * height]
+        .chunks(info.line_size)
+        .flat_map(|line| {
+            line[..width * channels].chunks(channels).map(|p| {
+                if channels < 3 {
+                    [p[0]; 3] // Grayscale, with or without alpha
+                } else {
+                    [p[0], p[1], p[2]]
+                }
+            })
+        })
+        .collect();
+    Ok(quantize(&rgb, width, height, lines))
+}
+
+/// Scales an image of RGB pixels to `WIDTH` by `lines` with nearest neighbour sampling and
+/// maps every pixel to the closest palette color, as packed 4 bit pixels
+#[must_use]
+pub fn quantize(rgb: &[[u8; 3]], width: usize, height: usize, lines: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(WIDTH / 2 * lines);
+    if width == 0 || height == 0 || rgb.len() < width * height {
+        pixels.resize(WIDTH / 2 * lines, 0);
+        return pixels;
+    }
+    for y in 0..lines {
+        let row = &rgb[y * height / lines * width..][..width];
+        for x in (0..WIDTH).step_by(2) {
+            let left = nearest_color(row[x * width / WIDTH]);
+            let right = nearest_color(row[(x + 1) * width / WIDTH]);
+            pixels.push(right << 4 | left); // Low nibble holds the leftmost pixel
+        }
+    }
+    pixels
+}
+
+/// Index of the palette color closest to `rgb`
+#[must_use]
+pub fn nearest_color(rgb: [u8; 3]) -> u8 {
+    let distance = |color: &[u8; 4]| -> u32 {
+        (0..3)
+            .map(|i| u32::from(color[i + 1].abs_diff(rgb[i])).pow(2))
+            .sum()
+    };
+    (0u8..)
+        .zip(COLORS.iter())
+        .min_by_key(|(_, color)| distance(color))
+        .map_or(0, |(index, _)| index)
+}
@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+/// Network trouble to simulate on a stream
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Impairment {
+    pub loss: f64,        // Fraction of packets dropped
+    pub reorder: f64,     // Fraction of packets swapped with the next one
+    pub jitter: Duration, // Largest random delay added to a packet
+}
+
+/// Queues the packets of one stream until they are due, with loss, reordering and jitter
+pub struct Scheduler<T> {
+    impairment: Impairment,
+    rng: fastrand::Rng,
+    queue: Vec<(Instant, T)>, // Ordered by the time packets are due
+    held: Option<T>,          // Packet to send after the next one
+}
+
+impl<T> Scheduler<T> {
+    /// Scheduler with a random generator seeded with `seed`, so runs can be repeated
+    #[must_use]
+    pub fn new(impairment: Impairment, seed: u64) -> Self {
+        Self {
+            impairment,
+            rng: fastrand::Rng::with_seed(seed),
+            queue: Vec::new(),
+            held: None,
+        }
+    }
+
+    /// Queues `packet` to be sent at `due`, unless it gets lost
+    pub fn push(&mut self, due: Instant, packet: T) {
+        if self.rng.f64() < self.impairment.loss {
+            return;
+        }
+        if self.held.is_none() && self.rng.f64() < self.impairment.reorder {
+            self.held = Some(packet);
+            return;
+        }
+        let due = due + self.impairment.jitter.mul_f64(self.rng.f64());
+        self.insert(due, packet);
+        if let Some(held) = self.held.take() {
+            self.insert(due, held);
+        }
+    }
+
+    /// Removes the next packet if it is due at `now`
+    pub fn pop(&mut self, now: Instant) -> Option<T> {
+        if self.queue.first().is_some_and(|(due, _)| *due <= now) {
+            Some(self.queue.remove(0).1)
+        } else {
+            None
+        }
+    }
+
+    /// Time the next packet is due, if any is queued
+    #[must_use]
+    pub fn next_due(&self) -> Option<Instant> {
+        self.queue.first().map(|(due, _)| *due)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && self.held.is_none()
+    }
+
+    /// Queues a held packet at `now`, for when no packet follows it
+    pub fn flush(&mut self, now: Instant) {
+        if let Some(held) = self.held.take() {
+            self.insert(now, held);
+        }
+    }
+
+    fn insert(&mut self, due: Instant, packet: T) {
+        let index = self.queue.partition_point(|(queued, _)| *queued <= due);
+        self.queue.insert(index, (due, packet));
+    }
+}
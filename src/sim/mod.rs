@@ -0,0 +1,11 @@
+mod image;
+mod impair;
+mod packets;
+mod pattern;
+mod wav;
+
+pub use image::{load_png, nearest_color, quantize};
+pub use impair::{Impairment, Scheduler};
+pub use packets::{LINES_PER_PACKET, audio_packet, video_packets};
+pub use pattern::{Tone, test_pattern};
+pub use wav::load_wav;
@@ -0,0 +1,39 @@
+use crate::codec::{AudioStream, SAMPLES_PER_PACKET, VideoHeader};
+use crate::constants::WIDTH;
+
+pub const LINES_PER_PACKET: usize = 4; // As sent by the Ultimate
+
+/// Splits one frame of packed 4 bit pixels, `WIDTH` pixels per line, into raw video packets
+///
+/// Every packet gets the next sequence number from `seq`, the last one of the frame has the
+/// end-of-frame marker set.
+///
+/// # Panics
+/// Panics if the frame has more than 32767 lines
+#[must_use]
+pub fn video_packets(pixels: &[u8], frame: u16, seq: &mut u16) -> Vec<Vec<u8>> {
+    let stride = WIDTH / 2;
+    let chunks = pixels.chunks(stride * LINES_PER_PACKET);
+    let count = chunks.len();
+
+    chunks
+        .enumerate()
+        .map(|(i, chunk)| {
+            let line = u16::try_from(i * LINES_PER_PACKET).expect("Too many lines");
+            let lpp = u8::try_from(chunk.len() / stride).unwrap_or_default();
+            let width = u16::try_from(WIDTH).unwrap_or_default();
+            let mut header = VideoHeader::new(*seq, frame, line, width, lpp, 4);
+            if i + 1 == count {
+                header = header.last_line();
+            }
+            *seq = seq.wrapping_add(1);
+            header.encode(chunk)
+        })
+        .collect()
+}
+
+/// Builds an audio packet of left and right sample pairs
+#[must_use]
+pub fn audio_packet(seq: u16, samples: &[[i16; 2]; SAMPLES_PER_PACKET]) -> Vec<u8> {
+    AudioStream::new(seq, samples).encode()
+}
@@ -0,0 +1,58 @@
+use std::f64::consts::TAU;
+
+use crate::codec::SAMPLES_PER_PACKET;
+use crate::constants::WIDTH;
+
+const BAR_HEIGHT: usize = 8; // Lines of the moving bar
+const VOLUME: f64 = 0.25;
+
+/// Color bars of all 16 colors with a black bar that moves down one line per frame, as packed
+/// 4 bit pixels
+#[must_use]
+pub fn test_pattern(frame: u16, lines: usize) -> Vec<u8> {
+    let stride = WIDTH / 2;
+    let bar = usize::from(frame) % lines;
+    let mut pixels = Vec::with_capacity(stride * lines);
+
+    for y in 0..lines {
+        let in_bar = (y + lines - bar) % lines < BAR_HEIGHT;
+        pixels.extend((0..stride).map(|x| {
+            if in_bar {
+                0
+            } else {
+                // Two pixels per byte, 16 bars of 24 pixels
+                let color = u8::try_from(x * 2 * 16 / WIDTH).unwrap_or_default();
+                color << 4 | color
+            }
+        }));
+    }
+    pixels
+}
+
+/// Sine wave on both channels
+pub struct Tone {
+    step: f64,  // Phase increment per sample
+    phase: f64, // Carries over to the next packet
+}
+
+impl Tone {
+    #[must_use]
+    pub fn new(frequency: f64, sample_rate: f64) -> Self {
+        Self {
+            step: TAU * frequency / sample_rate,
+            phase: 0.,
+        }
+    }
+
+    /// Samples for the next audio packet
+    pub fn next_packet(&mut self) -> [[i16; 2]; SAMPLES_PER_PACKET] {
+        let mut samples = [[0; 2]; SAMPLES_PER_PACKET];
+        for pair in &mut samples {
+            #[allow(clippy::cast_possible_truncation)] // Within range of i16 by VOLUME
+            let sample = (self.phase.sin() * VOLUME * f64::from(i16::MAX)) as i16;
+            *pair = [sample, sample];
+            self.phase = (self.phase + self.step) % TAU;
+        }
+        samples
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+/// Loads a WAV file as left and right sample pairs and returns them with the sample rate
+///
+/// Mono files are played on both channels, channels beyond the second are dropped.
+///
+/// # Errors
+/// Returns an error if the file can't be read or is not a valid WAV file
+pub fn load_wav(path: &Path) -> Result<(Vec<[i16; 2]>, u32), String> {
+    let error = |e: hound::Error| format!("Unable to read {}: {e}", path.display());
+    let mut reader = hound::WavReader::open(path).map_err(error)?;
+    let spec = reader.spec();
+
+    let samples: Vec<i16> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|s| s.map(|s| to_i16(f64::from(s))))
+            .collect::<Result<_, _>>()
+            .map_err(error)?,
+        hound::SampleFormat::Int => {
+            let scale = f64::from(1u32 << (spec.bits_per_sample - 1).min(31));
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| to_i16(f64::from(s) / scale)))
+                .collect::<Result<_, _>>()
+                .map_err(error)?
+        }
+    };
+
+    let channels = usize::from(spec.channels.max(1));
+    let pairs = samples
+        .chunks_exact(channels)
+        .map(|frame| [frame[0], frame[channels.min(2) - 1]])
+        .collect();
+    Ok((pairs, spec.sample_rate))
+}
+
+#[allow(clippy::cast_possible_truncation)] // Clamped to the range of i16
+fn to_i16(sample: f64) -> i16 {
+    (sample * 32768.).clamp(f64::from(i16::MIN), f64::from(i16::MAX)) as i16
+}
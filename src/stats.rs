@@ -0,0 +1,236 @@
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::network::{DropCounts, PacketError};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const MAX_GAP: u16 = 1000; // Larger sequence jumps are treated as a restarted stream
+const HISTORY: u16 = 64; // Number of sequence numbers remembered to spot duplicates
+
+pub type Stats = Arc<Mutex<Statistics>>;
+
+/// Counters of a single stream, fed by the protocol handler
+#[derive(Debug, Default)]
+pub struct StreamStats {
+    received: u64,
+    lost: u64,
+    duplicate: u64,
+    reordered: u64,
+    drops: DropCounts,
+    source: Option<SocketAddr>, // Sender of the last valid packet
+    signal: bool,               // Packets arrived since the stream last went silent
+    highest: Option<u16>,       // Highest sequence number seen
+    history: u64,               // Bit `n` is set if `highest - n` was received
+    last_arrival: Option<Instant>,
+    interval: f64, // Smoothed inter-arrival time in seconds
+    jitter: f64,   // Smoothed deviation from `interval` in seconds
+    window_start: Option<Instant>,
+    window_packets: u32,
+    window_frames: u32,
+    packet_rate: f64,
+    frame_rate: f64,
+}
+
+impl StreamStats {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a valid packet with sequence number `seq` received at `now`
+    pub fn packet(&mut self, now: Instant, seq: u16) {
+        self.received += 1;
+        self.signal = true;
+        self.arrival(now);
+        self.window(now, 1, 0);
+
+        let Some(highest) = self.highest else {
+            self.highest = Some(seq);
+            self.history = 1;
+            return;
+        };
+
+        let ahead = seq.wrapping_sub(highest);
+        let behind = highest.wrapping_sub(seq);
+        if ahead == 0 {
+            self.duplicate += 1;
+        } else if ahead < MAX_GAP {
+            self.lost += u64::from(ahead - 1);
+            self.history = self.history.checked_shl(u32::from(ahead)).unwrap_or(0) | 1;
+            self.highest = Some(seq);
+        } else if behind < HISTORY {
+            let bit = 1 << behind;
+            if self.history & bit == 0 {
+                // Arrived late, it was counted as lost before
+                self.history |= bit;
+                self.reordered += 1;
+                self.lost = self.lost.saturating_sub(1);
+            } else {
+                self.duplicate += 1;
+            }
+        } else if behind < MAX_GAP {
+            // Too old to tell apart from a duplicate
+            self.reordered += 1;
+        } else {
+            self.highest = Some(seq);
+            self.history = 1;
+        }
+    }
+
+    /// Registers the sender of the last valid packet
+    pub fn source(&mut self, src: SocketAddr) {
+        self.source = Some(src);
+    }
+
+    /// Registers that the stream went silent and returns whether it had a signal until now.
+    /// Sequence tracking and rates restart with the next packet.
+    pub fn signal_lost(&mut self) -> bool {
+        let signal = std::mem::take(&mut self.signal);
+        self.highest = None;
+        self.history = 0;
+        self.last_arrival = None;
+        self.window_start = None;
+        self.window_packets = 0;
+        self.window_frames = 0;
+        self.packet_rate = 0.;
+        self.frame_rate = 0.;
+        signal
+    }
+
+    /// Counts a malformed datagram and returns the number of drops for the same reason
+    pub fn malformed(&mut self, error: &PacketError) -> u64 {
+        self.drops.count(error)
+    }
+
+    /// Registers a completed frame
+    pub fn frame(&mut self, now: Instant) {
+        self.window(now, 0, 1);
+    }
+
+    #[must_use]
+    pub fn drops(&self) -> &DropCounts {
+        &self.drops
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> StreamSnapshot {
+        StreamSnapshot {
+            received: self.received,
+            lost: self.lost,
+            duplicate: self.duplicate,
+            reordered: self.reordered,
+            malformed: self.drops.total(),
+            source: self.source,
+            signal: self.signal,
+            jitter: Duration::from_secs_f64(self.jitter),
+            packet_rate: self.packet_rate,
+            frame_rate: self.frame_rate,
+        }
+    }
+
+    // Jitter is smoothed the same way as in RFC 3550
+    fn arrival(&mut self, now: Instant) {
+        if let Some(last) = self.last_arrival {
+            let interval = now.saturating_duration_since(last).as_secs_f64();
+            if self.interval == 0. {
+                self.interval = interval;
+            }
+            self.jitter += ((interval - self.interval).abs() - self.jitter) / 16.;
+            self.interval += (interval - self.interval) / 16.;
+        }
+        self.last_arrival = Some(now);
+    }
+
+    fn window(&mut self, now: Instant, packets: u32, frames: u32) {
+        let start = *self.window_start.get_or_insert(now);
+        self.window_packets += packets;
+        self.window_frames += frames;
+
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed >= RATE_WINDOW {
+            let seconds = elapsed.as_secs_f64();
+            self.packet_rate = f64::from(self.window_packets) / seconds;
+            self.frame_rate = f64::from(self.window_frames) / seconds;
+            self.window_start = Some(now);
+            self.window_packets = 0;
+            self.window_frames = 0;
+        }
+    }
+}
+
+/// Counters of both streams and the audio buffer
+#[derive(Debug, Default)]
+pub struct Statistics {
+    pub video: StreamStats,
+    pub audio: StreamStats,
+    audio_fill: usize,
+    audio_underruns: u64,
+    audio_overruns: u64,
+}
+
+impl Statistics {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the state of the audio `SampleQueue`
+    pub fn audio_buffer(&mut self, fill: usize, underruns: u64, overruns: u64) {
+        self.audio_fill = fill;
+        self.audio_underruns = underruns;
+        self.audio_overruns = overruns;
+    }
+
+    #[must_use]
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            video: self.video.snapshot(),
+            audio: self.audio.snapshot(),
+            audio_fill: self.audio_fill,
+            audio_underruns: self.audio_underruns,
+            audio_overruns: self.audio_overruns,
+        }
+    }
+}
+
+/// Point in time copy of the counters of a single stream
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StreamSnapshot {
+    pub received: u64,
+    pub lost: u64,
+    pub duplicate: u64,
+    pub reordered: u64,
+    pub malformed: u64,
+    pub source: Option<SocketAddr>,
+    pub signal: bool, // False before the first packet and after a watchdog timeout
+    pub jitter: Duration, // Smoothed inter-arrival jitter
+    pub packet_rate: f64, // Packets per second
+    pub frame_rate: f64, // Completed frames per second, video only
+}
+
+impl StreamSnapshot {
+    /// Fraction of packets lost, between 0 and 1
+    #[must_use]
+    pub fn loss(&self) -> f64 {
+        let expected = self.received + self.lost;
+        if expected == 0 {
+            0.
+        } else {
+            // Precision loss only matters after 2^52 packets
+            #[allow(clippy::cast_precision_loss)]
+            let loss = self.lost as f64 / expected as f64;
+            loss
+        }
+    }
+}
+
+/// Point in time copy of all counters
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub video: StreamSnapshot,
+    pub audio: StreamSnapshot,
+    pub audio_fill: usize, // Samples waiting in the audio buffer
+    pub audio_underruns: u64,
+    pub audio_overruns: u64,
+}
@@ -0,0 +1,173 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::codec::SAMPLES_PER_PACKET;
+
+const FRAME_WINDOW: Duration = Duration::from_secs(1);
+const AUDIO_MIN_WINDOW: Duration = Duration::from_secs(2);
+const AUDIO_MAX_WINDOW: Duration = Duration::from_mins(1);
+const STREAM_GAP: Duration = Duration::from_secs(1); // Restart measuring after a gap this long
+const RATE_TOLERANCE: f64 = 0.01; // Largest deviation of a plausible measured sample rate
+
+pub type Timing = Arc<Mutex<TimingDetector>>;
+
+/// Timing of the machine sending the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingProfile {
+    Pal,
+    Ntsc,
+}
+
+impl TimingProfile {
+    /// Frames per second of the VIC-II
+    #[must_use]
+    pub fn frame_rate(self) -> f64 {
+        match self {
+            Self::Pal => 50.124_5,
+            Self::Ntsc => 59.826_1,
+        }
+    }
+
+    #[must_use]
+    pub fn frame_period(self) -> Duration {
+        Duration::from_secs_f64(1. / self.frame_rate())
+    }
+
+    /// Audio samples per second per channel sent by the Ultimate
+    #[must_use]
+    pub fn sample_rate(self) -> f64 {
+        match self {
+            Self::Pal => 47_982.887,
+            Self::Ntsc => 47_940.341,
+        }
+    }
+
+    /// Number of lines in the visible area
+    #[must_use]
+    pub fn visible_lines(self) -> usize {
+        match self {
+            Self::Pal => 272,
+            Self::Ntsc => 240,
+        }
+    }
+}
+
+/// Measures frame rate, lines per frame and audio sample rate of the incoming streams
+#[derive(Debug, Default)]
+pub struct TimingDetector {
+    frame_start: Option<Instant>, // Start of the current frame rate window
+    last_frame: Option<Instant>,
+    frames: u32, // Frames in the current window
+    frame_rate: Option<f64>,
+    lines: Option<usize>,
+    audio_start: Option<Instant>, // Start of the current sample rate window
+    last_audio: Option<(Instant, u16)>,
+    samples: u32, // Samples per channel in the current window
+    sample_rate: Option<f64>,
+}
+
+impl TimingDetector {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a completed frame of `lines` lines received at `now`
+    pub fn frame(&mut self, now: Instant, lines: usize) {
+        self.lines = Some(lines);
+        let gap = self
+            .last_frame
+            .is_none_or(|last| now.saturating_duration_since(last) >= STREAM_GAP);
+        self.last_frame = Some(now);
+
+        match self.frame_start {
+            Some(start) if !gap => {
+                self.frames += 1;
+                let elapsed = now.saturating_duration_since(start);
+                if elapsed >= FRAME_WINDOW {
+                    self.frame_rate = Some(f64::from(self.frames) / elapsed.as_secs_f64());
+                    self.frame_start = Some(now);
+                    self.frames = 0;
+                }
+            }
+            _ => {
+                self.frame_start = Some(now);
+                self.frames = 0;
+            }
+        }
+    }
+
+    /// Registers an audio packet with sequence number `seq` received at `now`
+    pub fn audio_packet(&mut self, now: Instant, seq: u16) {
+        match (self.audio_start, self.last_audio) {
+            (Some(start), Some((last, last_seq)))
+                if now.saturating_duration_since(last) < STREAM_GAP =>
+            {
+                let packets = seq.wrapping_sub(last_seq);
+                if packets == 0 || packets >= 0x8000 {
+                    // Duplicate or reordered packet
+                    return;
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let per_packet = SAMPLES_PER_PACKET as u32;
+                self.samples += u32::from(packets) * per_packet;
+                let elapsed = now.saturating_duration_since(start);
+                if elapsed >= AUDIO_MIN_WINDOW {
+                    self.sample_rate = Some(f64::from(self.samples) / elapsed.as_secs_f64());
+                }
+                if elapsed >= AUDIO_MAX_WINDOW {
+                    self.audio_start = Some(now);
+                    self.samples = 0;
+                }
+            }
+            _ => {
+                self.audio_start = Some(now);
+                self.samples = 0;
+            }
+        }
+        self.last_audio = Some((now, seq));
+    }
+
+    /// Measured frames per second
+    #[must_use]
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.frame_rate
+    }
+
+    /// Lines in the last frame
+    #[must_use]
+    pub fn lines(&self) -> Option<usize> {
+        self.lines
+    }
+
+    /// Measured audio samples per second per channel
+    #[must_use]
+    pub fn sample_rate(&self) -> Option<f64> {
+        self.sample_rate
+    }
+
+    /// Sample rate to play the audio at, the measured rate if it is close to the nominal rate
+    /// of the profile, otherwise the nominal rate. PAL is assumed until a profile is detected.
+    #[must_use]
+    pub fn stream_rate(&self) -> f64 {
+        let nominal = self.profile().unwrap_or(TimingProfile::Pal).sample_rate();
+        self.sample_rate
+            .filter(|rate| (rate / nominal - 1.).abs() <= RATE_TOLERANCE)
+            .unwrap_or(nominal)
+    }
+
+    /// Profile matching the measurements, the frame rate takes precedence over the line count
+    #[must_use]
+    pub fn profile(&self) -> Option<TimingProfile> {
+        let pal = match (self.frame_rate, self.lines) {
+            (Some(rate), _) => rate < 55.,
+            (None, Some(lines)) => lines > 256,
+            (None, None) => return None,
+        };
+        Some(if pal {
+            TimingProfile::Pal
+        } else {
+            TimingProfile::Ntsc
+        })
+    }
+}
@@ -1,3 +1,7 @@
+mod font;
+mod osd;
 mod render;
 
-pub use render::{Window, run_window};
+pub use font::glyph;
+pub use osd::{CHAR_SIZE, draw_no_signal, draw_osd, draw_text, osd_lines};
+pub use render::{Window, draw_frame, run_window, visible_rows};
@@ -0,0 +1,94 @@
+use super::font::glyph;
+use crate::constants::{COLORS, colors_to_u32};
+use crate::{StatsSnapshot, TimingProfile};
+
+pub const CHAR_SIZE: usize = 8;
+const MARGIN: usize = 8; // Distance between the display and the edge of the frame
+
+/// Draws `text` in `(foreground, background)` colors with its top left corner at `(x, y)`,
+/// clipped to the buffer
+pub fn draw_text(
+    buffer: &mut [u32],
+    width: usize,
+    (x, y): (usize, usize),
+    text: &str,
+    (fg, bg): (u32, u32),
+) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i * CHAR_SIZE;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            let line = (y + row) * width;
+            for col in 0..CHAR_SIZE {
+                let x_pos = left + col;
+                if x_pos >= width {
+                    break;
+                }
+                if let Some(pixel) = buffer.get_mut(line + x_pos) {
+                    *pixel = if bits & (0x80 >> col) != 0 { fg } else { bg };
+                }
+            }
+        }
+    }
+}
+
+/// Draws `lines` in the top left corner of the frame in light blue on blue
+pub fn draw_osd(buffer: &mut [u32], width: usize, lines: &[String]) {
+    let colors = (colors_to_u32(COLORS[14]), colors_to_u32(COLORS[6]));
+    let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    // One character of padding around the text
+    let blank = String::new();
+    let padded = std::iter::once(&blank).chain(lines).chain([&blank]);
+    for (i, line) in padded.enumerate() {
+        let position = (MARGIN, MARGIN + i * CHAR_SIZE);
+        draw_text(
+            buffer,
+            width,
+            position,
+            &format!(" {line:columns$} "),
+            colors,
+        );
+    }
+}
+
+/// Fills the frame with blue and centers a "NO SIGNAL" message in light blue
+pub fn draw_no_signal(buffer: &mut [u32], width: usize, height: usize) {
+    const MESSAGE: &str = "NO SIGNAL";
+    let (fg, bg) = (colors_to_u32(COLORS[14]), colors_to_u32(COLORS[6]));
+    buffer.fill(bg);
+
+    let x = width.saturating_sub(MESSAGE.len() * CHAR_SIZE) / 2;
+    let y = height.saturating_sub(CHAR_SIZE) / 2;
+    draw_text(buffer, width, (x, y), MESSAGE, (fg, bg));
+}
+
+/// Text lines of the on-screen display
+#[must_use]
+pub fn osd_lines(
+    stats: &StatsSnapshot,
+    geometry: (usize, usize, u8),
+    profile: Option<TimingProfile>,
+) -> Vec<String> {
+    let (width, height, bits) = geometry;
+    let timing = match profile {
+        Some(TimingProfile::Pal) => "PAL",
+        Some(TimingProfile::Ntsc) => "NTSC",
+        None => "-",
+    };
+    let source = stats
+        .video
+        .source
+        .map_or_else(|| "-".to_string(), |src| src.ip().to_string());
+
+    vec![
+        format!("FPS {:.1}", stats.video.frame_rate),
+        format!("VIDEO {width}X{height} {bits}BPP {timing}"),
+        format!("VIDEO LOSS {:.2}%", stats.video.loss() * 100.),
+        format!("AUDIO LOSS {:.2}%", stats.audio.loss() * 100.),
+        format!(
+            "AUDIO BUFFER {} UNDERRUNS {}",
+            stats.audio_fill, stats.audio_underruns
+        ),
+        format!("SOURCE {source}"),
+    ]
+}
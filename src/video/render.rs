@@ -1,24 +1,40 @@
-use minifb::{Key, WindowOptions};
+use minifb::{Key, KeyRepeat, WindowOptions};
+use std::ops::Range;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::Receiver;
 
+use super::{draw_no_signal, draw_osd, osd_lines};
+use crate::capture::capture_file_name;
 use crate::constants::{COLORS, HEIGHT, WIDTH, colors_to_u32};
+use crate::{Frame, Recording, Stats, Timing, TimingProfile};
+
+const DEFAULT_PERIOD: Duration = Duration::from_millis(16); // Until the timing is known
+const TITLE: &str = "U64 Viewer - ESC to exit";
 
 pub struct Window {
     pub width: usize,
     pub height: usize,
+    pub show_loss: bool, // Tint lines that were concealed from the previous frame
+    pub osd: bool,       // Show the on-screen display with stream statistics
 }
 
+/// Recording with the **R** key is only offered when `recording` is given, i.e. the input is
+/// the network
+///
 /// # Panics
-/// Panics if there are conversion errors
+/// Panics if unable to acquire a `timing`, `stats` or `recording` lock
 /// # Errors
 /// Returns an error if unable to open the window
 pub fn run_window(
     win_config: &Window,
     palette: Option<&[u32]>,
-    video_rx: &mut Receiver<Vec<u8>>,
+    video_rx: &mut Receiver<Frame>,
+    timing: &Timing,
+    stats: &Stats,
+    recording: Option<&Recording>,
 ) -> Result<(), String> {
     let mut window = minifb::Window::new(
-        "U64 Viewer - ESC to exit",
+        TITLE,
         win_config.width,
         win_config.height,
         WindowOptions {
@@ -29,45 +45,182 @@ pub fn run_window(
     )
     .map_err(|e| format!("ERROR: {e}"))?;
 
-    let mut frame = vec![0u32; WIDTH * HEIGHT].into_boxed_slice();
-    let colors: [[u8; 4]; 16] = if let Some(palette) = palette
+    let (mut width, mut height) = (WIDTH, HEIGHT);
+    let mut bits = 4;
+    let mut frame = vec![0u32; width * height].into_boxed_slice();
+    let mut display = frame.clone();
+    let colors = palette_colors(palette);
+
+    let mut show_loss = win_config.show_loss;
+    let mut osd = win_config.osd;
+    let mut current_title = TITLE.to_string();
+    let mut next_update = Instant::now();
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_key_pressed(Key::L, KeyRepeat::No) {
+            show_loss = !show_loss;
+        }
+        if window.is_key_pressed(Key::O, KeyRepeat::No) {
+            osd = !osd;
+        }
+        if let Some(recording) = recording
+            && window.is_key_pressed(Key::R, KeyRepeat::No)
+        {
+            toggle_recording(recording);
+        }
+        let title = recording.map_or_else(|| TITLE.to_string(), recording_title);
+        if title != current_title {
+            window.set_title(&title);
+            current_title = title;
+        }
+        while let Ok(video_frame) = video_rx.try_recv() {
+            if (video_frame.width, video_frame.height) != (width, height) {
+                (width, height) = (video_frame.width, video_frame.height);
+                frame = vec![0u32; width * height].into_boxed_slice();
+                display.clone_from(&frame);
+            }
+            bits = video_frame.bits;
+            draw_frame(&mut frame, &video_frame, &colors, show_loss);
+        }
+        let profile = timing
+            .lock()
+            .expect("Unable to acquire lock on timing")
+            .profile();
+
+        // Crop to the visible area of the detected machine
+        let rows = visible_rows(height, profile);
+        let snapshot = stats
+            .lock()
+            .expect("Unable to acquire lock on stats")
+            .snapshot();
+        let signal = snapshot.video.signal;
+        let buffer = if osd || !signal {
+            if signal {
+                display.copy_from_slice(&frame);
+            } else {
+                draw_no_signal(
+                    &mut display[rows.start * width..rows.end * width],
+                    width,
+                    rows.len(),
+                );
+            }
+            if osd {
+                let lines = osd_lines(&snapshot, (width, height, bits), profile);
+                draw_osd(
+                    &mut display[rows.start * width..rows.end * width],
+                    width,
+                    &lines,
+                );
+            }
+            &display
+        } else {
+            &frame
+        };
+        _ = window.update_with_buffer(
+            &buffer[rows.start * width..rows.end * width],
+            width,
+            rows.len(),
+        );
+
+        // Pace updates to the frame rate of the sending machine
+        next_update += profile.map_or(DEFAULT_PERIOD, TimingProfile::frame_period);
+        let now = Instant::now();
+        if next_update > now {
+            std::thread::sleep(next_update - now);
+        } else {
+            next_update = now;
+        }
+    }
+    Ok(())
+}
+
+// Colors of an alternate palette or the default C64 colors
+fn palette_colors(palette: Option<&[u32]>) -> [[u8; 4]; 16] {
+    if let Some(palette) = palette
         && !palette.is_empty()
     {
         let mut result = [[0u8; 4]; 16];
-        for (i, &color) in palette.iter().enumerate() {
+        for (entry, &color) in result.iter_mut().zip(palette) {
             // Extract RGB from hex color (assuming format 0xRRGGBB)
-            let r = u8::try_from((color >> 16) & 0xFF).expect("conversion error");
-            let g = u8::try_from((color >> 8) & 0xFF).expect("conversion error");
-            let b = u8::try_from(color & 0xFF).expect("conversion error");
-            result[i] = [0, r, g, b]; // ARGB format with alpha = 0
+            let [_, r, g, b] = color.to_be_bytes();
+            *entry = [0, r, g, b]; // ARGB format with alpha = 0
         }
         result
     } else {
         COLORS
+    }
+}
+
+// Window title, naming the capture file while recording
+fn recording_title(recording: &Recording) -> String {
+    recording
+        .lock()
+        .expect("Unable to acquire lock on recording")
+        .path()
+        .map_or_else(
+            || TITLE.to_string(),
+            |path| format!("U64 Viewer - Recording {} - ESC to exit", path.display()),
+        )
+}
+
+// Stops a running recording or starts a new one in the working directory
+fn toggle_recording(recording: &Recording) {
+    let mut recorder = recording
+        .lock()
+        .expect("Unable to acquire lock on recording");
+    let result = if recorder.path().is_some() {
+        recorder.stop().map(|_| ())
+    } else {
+        recorder.start(&capture_file_name())
     };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+    }
+}
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        while let Ok(video_buffer) = video_rx.try_recv() {
-            let mut pos = 0;
-            for y_pos in 0..video_buffer.len() {
-                for x_pos in 0..192 {
-                    if pos >= video_buffer.len() {
-                        break;
-                    }
-                    let byte = video_buffer[pos] as usize;
-                    let frame_pos = (y_pos * WIDTH) + 2 * x_pos;
-                    if frame_pos >= frame.len() {
-                        continue;
-                    }
-                    frame[frame_pos] = colors_to_u32(colors[byte & 0x0f]);
-                    frame[frame_pos + 1] = colors_to_u32(colors[byte >> 4]);
-                    pos += 1;
-                }
+/// Rows of a frame of `height` lines in the visible area of `profile`, the border lines past
+/// it are cropped evenly from the top and bottom
+#[must_use]
+pub fn visible_rows(height: usize, profile: Option<TimingProfile>) -> Range<usize> {
+    let rows = profile.map_or(height, |p| p.visible_lines().min(height));
+    let top = (height - rows) / 2;
+    top..top + rows
+}
+
+/// Unpacks the color indices of `video_frame` into `buffer`, one pixel per `u32` and
+/// `video_frame.width` pixels per row
+///
+/// Rows that don't fit in `buffer` and lines missing from the frame are left alone, whatever
+/// the frame claims its geometry is.
+pub fn draw_frame(buffer: &mut [u32], video_frame: &Frame, colors: &[[u8; 4]; 16], tint: bool) {
+    let rows = buffer.chunks_mut(video_frame.width.max(1));
+    let lines = video_frame.pixels.chunks(video_frame.stride().max(1));
+    for ((row, line), &concealed) in rows.zip(lines).zip(&video_frame.concealed) {
+        draw_line(row, line, video_frame.bits, colors, tint && concealed);
+    }
+}
+
+// Unpacks one line of 4 or 8 bit color indices, the low nibble holds the leftmost pixel
+fn draw_line(row: &mut [u32], line: &[u8], bits: u8, colors: &[[u8; 4]; 16], tint: bool) {
+    if bits == 4 {
+        for (pixels, &byte) in row.chunks_mut(2).zip(line) {
+            pixels[0] = pixel(colors[usize::from(byte & 0x0f)], tint);
+            if let Some(right) = pixels.get_mut(1) {
+                *right = pixel(colors[usize::from(byte >> 4)], tint);
             }
         }
-        _ = window.update_with_buffer(&frame, WIDTH, HEIGHT);
-        // TODO: Do we really need this sleep?
-        std::thread::sleep(std::time::Duration::from_millis(16));
+    } else {
+        for (pixel_out, &byte) in row.iter_mut().zip(line) {
+            *pixel_out = pixel(colors[usize::from(byte & 0x0f)], tint);
+        }
+    }
+}
+
+// Concealed lines are darkened and shifted towards red
+fn pixel(color: [u8; 4], tint: bool) -> u32 {
+    let color = colors_to_u32(color);
+    if tint {
+        ((color >> 1) & 0x007F_7F7F) + 0x0080_0000
+    } else {
+        color
     }
-    Ok(())
 }
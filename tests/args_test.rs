@@ -1,5 +1,11 @@
+#![allow(clippy::needless_borrows_for_generic_args, clippy::unreadable_literal)]
+
 use clap::Parser;
+use lib::TimingProfile;
 use lib::args::Args;
+use lib::audio::{AudioOutput, DeviceSelector, WavFormat};
+use lib::network::Interface;
+use lib::replay::Speed;
 use std::net::Ipv4Addr;
 
 #[test]
@@ -256,3 +262,189 @@ fn test_long_form_multicast_addresses() {
     assert_eq!(args.video_maddr, Ipv4Addr::new(239, 100, 100, 100));
     assert_eq!(args.audio_maddr, Ipv4Addr::new(239, 200, 200, 200));
 }
+
+#[test]
+fn test_show_loss_flag() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert!(!args.show_loss);
+
+    let args = Args::try_parse_from(["program", "--show-loss"]).unwrap();
+    assert!(args.show_loss);
+}
+
+#[test]
+fn test_osd_flag() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert!(!args.osd);
+
+    let args = Args::try_parse_from(["program", "--osd"]).unwrap();
+    assert!(args.osd);
+}
+
+#[test]
+fn test_latency() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.latency, 100);
+
+    let args = Args::try_parse_from(["program", "--latency", "40"]).unwrap();
+    assert_eq!(args.latency, 40);
+
+    assert!(Args::try_parse_from(["program", "--latency", "5"]).is_err());
+    assert!(Args::try_parse_from(["program", "--latency", "2000"]).is_err());
+}
+
+#[test]
+fn test_audio_output() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.audio_output(), AudioOutput::default());
+    assert!(!args.list_audio_devices);
+
+    let args = Args::try_parse_from([
+        "program",
+        "--audio-host",
+        "jack",
+        "--audio-device",
+        "USB Audio",
+    ])
+    .unwrap();
+    assert_eq!(
+        args.audio_output(),
+        AudioOutput {
+            host: Some("jack".to_string()),
+            device: Some(DeviceSelector::Name("USB Audio".to_string())),
+        }
+    );
+
+    let args = Args::try_parse_from(["program", "--audio-device", "1"]).unwrap();
+    assert_eq!(args.audio_device, Some(DeviceSelector::Index(1)));
+
+    let args = Args::try_parse_from(["program", "--list-audio-devices"]).unwrap();
+    assert!(args.list_audio_devices);
+}
+
+#[test]
+fn test_signal_timeout() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.signal_timeout, 2);
+
+    let args = Args::try_parse_from(["program", "--signal-timeout", "5"]).unwrap();
+    assert_eq!(args.signal_timeout, 5);
+
+    assert!(Args::try_parse_from(["program", "--signal-timeout", "0"]).is_err());
+}
+
+#[test]
+fn test_interface() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.interface, None);
+
+    let args = Args::try_parse_from(["program", "--interface", "10.0.0.2"]).unwrap();
+    assert_eq!(
+        args.interface,
+        Some(Interface::Address(Ipv4Addr::new(10, 0, 0, 2)))
+    );
+
+    let args = Args::try_parse_from(["program", "-i", "eth1"]).unwrap();
+    assert_eq!(args.interface, Some(Interface::Name("eth1".to_string())));
+}
+
+#[test]
+fn test_recv_buffer() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.recv_buffer, 2 * 1024 * 1024);
+
+    let args = Args::try_parse_from(["program", "--recv-buffer", "8388608"]).unwrap();
+    assert_eq!(args.recv_buffer, 8_388_608);
+}
+
+#[test]
+fn test_source_selection() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert!(args.allow_source.is_empty());
+    assert!(!args.demux);
+    assert_eq!(args.source, None);
+
+    let args = Args::try_parse_from([
+        "program",
+        "--allow-source",
+        "192.168.1.64,192.168.1.65",
+        "--demux",
+        "--source",
+        "192.168.1.65",
+    ])
+    .unwrap();
+    assert_eq!(
+        args.allow_source,
+        [
+            Ipv4Addr::new(192, 168, 1, 64),
+            Ipv4Addr::new(192, 168, 1, 65)
+        ]
+    );
+    assert!(args.demux);
+    assert_eq!(args.source, Some(Ipv4Addr::new(192, 168, 1, 65).into()));
+
+    assert!(Args::try_parse_from(["program", "--allow-source", "ultimate"]).is_err());
+}
+
+#[test]
+fn test_record() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.record, None);
+
+    let args = Args::try_parse_from(["program", "--record", "session.u64cap"]).unwrap();
+    assert_eq!(args.record, Some("session.u64cap".into()));
+
+    // Only the network input can be recorded
+    assert!(Args::try_parse_from(["program", "-r", "a.u64cap", "--replay", "b.u64cap"]).is_err());
+    assert!(Args::try_parse_from(["program", "-r", "a.u64cap", "--synthetic"]).is_err());
+}
+
+#[test]
+fn test_replay() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.replay, None);
+    assert_eq!(args.speed, Speed::Factor(1.));
+
+    let args =
+        Args::try_parse_from(["program", "--replay", "dump.pcapng", "--speed", "max"]).unwrap();
+    assert_eq!(args.replay, Some("dump.pcapng".into()));
+    assert_eq!(args.speed, Speed::Max);
+
+    assert!(Args::try_parse_from(["program", "--speed", "0"]).is_err());
+}
+
+#[test]
+fn test_synthetic() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.synthetic, None);
+
+    let args = Args::try_parse_from(["program", "--synthetic"]).unwrap();
+    assert_eq!(args.synthetic, Some(TimingProfile::Pal));
+
+    let args = Args::try_parse_from(["program", "--synthetic", "NTSC"]).unwrap();
+    assert_eq!(args.synthetic, Some(TimingProfile::Ntsc));
+
+    assert!(Args::try_parse_from(["program", "--synthetic", "secam"]).is_err());
+    assert!(Args::try_parse_from(["program", "--synthetic", "--replay", "dump.pcap"]).is_err());
+}
+
+#[test]
+fn test_record_wav() {
+    let args = Args::try_parse_from(["program"]).unwrap();
+    assert_eq!(args.record_wav, None);
+    assert_eq!(args.wav_format, WavFormat::Pcm16);
+
+    let args = Args::try_parse_from([
+        "program",
+        "--mute",
+        "--record-wav",
+        "tune.wav",
+        "--wav-format",
+        "float",
+    ])
+    .unwrap();
+    assert_eq!(args.record_wav, Some("tune.wav".into()));
+    assert_eq!(args.wav_format, WavFormat::Float);
+
+    assert!(Args::try_parse_from(["program", "--wav-format", "pcm8"]).is_err());
+}
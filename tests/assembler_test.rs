@@ -0,0 +1,283 @@
+use lib::network::VideoPacket;
+use lib::{Frame, FrameAssembler, HEIGHT, WIDTH};
+use std::borrow::Cow;
+
+const BYTES_PER_LINE: usize = WIDTH / 2;
+const LAST_LINE: u16 = 0x8000;
+
+fn packet_lines() -> Vec<u16> {
+    let last = u16::try_from(HEIGHT - 4).unwrap();
+    (0..last).step_by(4).chain([last | LAST_LINE]).collect()
+}
+
+fn packet(frame: u16, line: u16, width: u16, fill: u8) -> VideoPacket<'static> {
+    VideoPacket {
+        seq: 0,
+        frame,
+        line,
+        width,
+        lpp: 4,
+        bits: 4,
+        data: Cow::Owned(vec![fill; usize::from(width) * 2]),
+    }
+}
+
+fn push(assembler: &mut FrameAssembler, frame: u16, line: u16, fill: u8) -> Option<Frame> {
+    assembler.push(&packet(frame, line, 384, fill))
+}
+
+// Feeds a complete frame where every packet is filled with its own line number
+fn push_frame(assembler: &mut FrameAssembler, frame: u16, lines: &[u16]) -> Option<Frame> {
+    let mut result = None;
+    for &line in lines {
+        let fill = u8::try_from((line & !LAST_LINE) / 4).unwrap();
+        if let Some(f) = push(assembler, frame, line, fill) {
+            result = Some(f);
+        }
+    }
+    result
+}
+
+fn band(frame: &Frame, line: usize) -> &[u8] {
+    &frame.pixels[line * BYTES_PER_LINE..(line + 4) * BYTES_PER_LINE]
+}
+
+#[test]
+fn test_first_partial_frame_is_skipped() {
+    let mut assembler = FrameAssembler::new();
+    assert!(push_frame(&mut assembler, 1, &packet_lines()).is_none());
+}
+
+#[test]
+fn test_complete_frame_has_full_size() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+    let frame = push_frame(&mut assembler, 2, &packet_lines()).unwrap();
+    assert_eq!(frame.pixels.len(), BYTES_PER_LINE * HEIGHT);
+}
+
+#[test]
+fn test_packets_placed_by_line_number() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+
+    let mut lines = packet_lines();
+    let last = lines.pop().unwrap();
+    lines.reverse();
+    lines.push(last);
+    let frame = push_frame(&mut assembler, 2, &lines).unwrap();
+
+    assert!(band(&frame, 0).iter().all(|&b| b == 0));
+    assert!(band(&frame, 4).iter().all(|&b| b == 1));
+    assert!(band(&frame, 100).iter().all(|&b| b == 25));
+    assert!(band(&frame, HEIGHT - 4).iter().all(|&b| b == 67));
+}
+
+#[test]
+fn test_missing_end_marker_flushes_on_next_frame() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+
+    let mut lines = packet_lines();
+    lines.pop();
+    assert!(push_frame(&mut assembler, 2, &lines).is_none());
+
+    let frame = push(&mut assembler, 3, 0, 0xAA).unwrap();
+    assert!(band(&frame, 4).iter().all(|&b| b == 1));
+    assert!(band(&frame, 0).iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_late_packet_of_previous_frame_is_dropped() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+
+    assert!(push(&mut assembler, 2, 0, 0x11).is_none());
+    assert!(push(&mut assembler, 1, 4, 0xFF).is_none());
+    let frame = push_frame(&mut assembler, 2, &packet_lines()[1..]).unwrap();
+
+    assert!(band(&frame, 0).iter().all(|&b| b == 0x11));
+    assert!(band(&frame, 4).iter().all(|&b| b == 1));
+}
+
+#[test]
+fn test_packet_after_end_marker_is_dropped() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+    push_frame(&mut assembler, 2, &packet_lines()).unwrap();
+
+    assert!(push(&mut assembler, 2, 8, 0xFF).is_none());
+    let frame = push_frame(&mut assembler, 3, &packet_lines()).unwrap();
+    assert!(band(&frame, 8).iter().all(|&b| b == 2));
+}
+
+#[test]
+fn test_frame_number_wraps_around() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, u16::MAX, &packet_lines());
+    assert!(push_frame(&mut assembler, 0, &packet_lines()).is_some());
+    assert!(push(&mut assembler, u16::MAX, 0, 0xFF).is_none());
+}
+
+#[test]
+fn test_frame_counter_reset_restarts_assembly() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 5000, &packet_lines());
+    push_frame(&mut assembler, 5001, &packet_lines()).unwrap();
+
+    // The sender restarted counting from 0, its frames are shown at once
+    let frame = push_frame(&mut assembler, 0, &packet_lines()).unwrap();
+    assert!(frame.concealed.iter().all(|&c| !c));
+    assert!(push_frame(&mut assembler, 1, &packet_lines()).is_some());
+    // Packets of the frames before the restart are late again
+    assert!(push(&mut assembler, 0, 4, 0xFF).is_none());
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn test_out_of_range_line_panics() {
+    // `decode_video` drops such packets, so they never get here
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+    push(&mut assembler, 2, 0x7FFC, 0xFF);
+}
+
+#[test]
+fn test_complete_frame_has_no_concealed_lines() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+    let frame = push_frame(&mut assembler, 2, &packet_lines()).unwrap();
+    assert_eq!(frame.concealed.len(), HEIGHT);
+    assert!(frame.concealed.iter().all(|&c| !c));
+}
+
+#[test]
+fn test_missing_band_filled_from_previous_frame() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+    push_frame(&mut assembler, 2, &packet_lines()).unwrap();
+
+    push(&mut assembler, 3, 0, 0x11);
+    push(&mut assembler, 3, 8, 0x22);
+    let frame = push(
+        &mut assembler,
+        3,
+        u16::try_from(HEIGHT - 4).unwrap() | LAST_LINE,
+        0x33,
+    )
+    .unwrap();
+
+    assert!(band(&frame, 0).iter().all(|&b| b == 0x11));
+    assert!(band(&frame, 4).iter().all(|&b| b == 1));
+    assert!(band(&frame, 8).iter().all(|&b| b == 0x22));
+    assert!(band(&frame, 12).iter().all(|&b| b == 3));
+    assert!(band(&frame, HEIGHT - 4).iter().all(|&b| b == 0x33));
+
+    assert!(!frame.concealed[0..4].iter().any(|&c| c));
+    assert!(frame.concealed[4..8].iter().all(|&c| c));
+    assert!(!frame.concealed[8..12].iter().any(|&c| c));
+    assert!(frame.concealed[12..HEIGHT - 4].iter().all(|&c| c));
+    assert!(!frame.concealed[HEIGHT - 4..].iter().any(|&c| c));
+}
+
+#[test]
+fn test_concealment_does_not_leak_into_next_frame() {
+    let mut assembler = FrameAssembler::new();
+    push_frame(&mut assembler, 1, &packet_lines());
+
+    let mut lines = packet_lines();
+    lines.remove(1);
+    let frame = push_frame(&mut assembler, 2, &lines).unwrap();
+    assert!(frame.concealed[4]);
+
+    let frame = push_frame(&mut assembler, 3, &packet_lines()).unwrap();
+    assert!(!frame.concealed[4]);
+}
+
+// Feeds a complete frame of `height` lines with the given width
+fn push_sized(
+    assembler: &mut FrameAssembler,
+    frame: u16,
+    width: u16,
+    height: u16,
+) -> Option<Frame> {
+    let mut result = None;
+    for line in (0..height).step_by(4) {
+        let line = if line + 4 >= height {
+            line | LAST_LINE
+        } else {
+            line
+        };
+        if let Some(f) = assembler.push(&packet(frame, line, width, 0x21)) {
+            result = Some(f);
+        }
+    }
+    result
+}
+
+#[test]
+fn test_frame_height_from_end_marker() {
+    let mut assembler = FrameAssembler::new();
+    push_sized(&mut assembler, 1, 384, 240);
+    let frame = push_sized(&mut assembler, 2, 384, 240).unwrap();
+
+    assert_eq!((frame.width, frame.height, frame.bits), (384, 240, 4));
+    assert_eq!(frame.stride(), 192);
+    assert_eq!(frame.pixels.len(), 192 * 240);
+    assert_eq!(frame.concealed.len(), 240);
+}
+
+#[test]
+fn test_frame_width_from_header() {
+    let mut assembler = FrameAssembler::new();
+    push_sized(&mut assembler, 1, 512, 272);
+    let frame = push_sized(&mut assembler, 2, 512, 272).unwrap();
+
+    assert_eq!((frame.width, frame.height), (512, 272));
+    assert_eq!(frame.pixels.len(), 256 * 272);
+    assert!(frame.pixels.iter().all(|&b| b == 0x21));
+}
+
+#[test]
+fn test_height_follows_highest_line_without_end_marker() {
+    let mut assembler = FrameAssembler::new();
+    push_sized(&mut assembler, 1, 384, 240);
+
+    for line in (0..200).step_by(4) {
+        push(&mut assembler, 2, line, 1);
+    }
+    let frame = push(&mut assembler, 3, 0, 1).unwrap();
+    assert_eq!(frame.height, 240);
+    assert!(frame.concealed[200..].iter().all(|&c| c));
+}
+
+#[test]
+fn test_geometry_change_resets_content() {
+    let mut assembler = FrameAssembler::new();
+    push_sized(&mut assembler, 1, 384, 272);
+    push_sized(&mut assembler, 2, 384, 272).unwrap();
+
+    push_sized(&mut assembler, 3, 320, 200);
+    for line in (0..196).step_by(4) {
+        assembler.push(&packet(4, line, 320, 0x44));
+    }
+    let frame = assembler
+        .push(&packet(4, 0xC4 | LAST_LINE, 320, 0x44))
+        .unwrap();
+    assert_eq!((frame.width, frame.height), (320, 200));
+    assert!(frame.pixels.iter().all(|&b| b == 0x44));
+}
+
+#[test]
+fn test_eight_bit_packets() {
+    let mut assembler = FrameAssembler::new();
+    let mut eight_bit = packet(1, 0x8000, 384, 0x05);
+    eight_bit.bits = 8;
+    eight_bit.data = Cow::Owned(vec![0x05; 384 * 4]);
+    assembler.push(&eight_bit);
+
+    eight_bit.frame = 2;
+    let frame = assembler.push(&eight_bit).unwrap();
+    assert_eq!((frame.width, frame.height, frame.bits), (384, 4, 8));
+    assert_eq!(frame.stride(), 384);
+}
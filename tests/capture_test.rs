@@ -0,0 +1,117 @@
+use lib::Recorder;
+use lib::capture::{
+    CaptureWriter, FOOTER_MAGIC, FileHeader, Footer, IndexEntry, MAGIC, RecordHeader, StreamKind,
+    VERSION,
+};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use zerocopy::FromBytes;
+
+fn src() -> SocketAddr {
+    "192.168.1.64:50000".parse().unwrap()
+}
+
+#[test]
+fn test_stream_kind_bytes() {
+    for kind in [StreamKind::Video, StreamKind::Audio] {
+        assert_eq!(StreamKind::from_byte(kind.to_byte()), Some(kind));
+    }
+    assert_eq!(StreamKind::from_byte(7), None);
+}
+
+#[test]
+fn test_capture_layout() {
+    let start = Instant::now();
+    let mut writer = CaptureWriter::new(Vec::new(), start).unwrap();
+    writer
+        .write(start, StreamKind::Video, src(), &[1, 2, 3])
+        .unwrap();
+    writer
+        .write(
+            start + Duration::from_millis(1500),
+            StreamKind::Audio,
+            src(),
+            &[4, 5],
+        )
+        .unwrap();
+    assert_eq!(writer.records(), 2);
+    let bytes = writer.finish().unwrap();
+
+    let (header, rest) = FileHeader::read_from_prefix(&bytes).unwrap();
+    assert_eq!(header.magic, MAGIC);
+    assert_eq!(header.version.get(), VERSION);
+
+    let (record, rest) = RecordHeader::read_from_prefix(rest).unwrap();
+    assert_eq!(record.time.get(), 0);
+    assert_eq!(StreamKind::from_byte(record.kind), Some(StreamKind::Video));
+    assert_eq!(record.source(), src());
+    assert_eq!(&rest[..3], &[1, 2, 3]);
+
+    let (record, rest) = RecordHeader::read_from_prefix(&rest[3..]).unwrap();
+    assert_eq!(record.time.get(), 1_500_000);
+    assert_eq!(record.len.get(), 2);
+    assert_eq!(StreamKind::from_byte(record.kind), Some(StreamKind::Audio));
+    assert_eq!(&rest[..2], &[4, 5]);
+
+    // One index entry per second of recording, followed by the footer
+    let (footer_bytes, _) = rest[2..].split_at(rest.len() - 2 - size_of::<Footer>());
+    let entries = <[IndexEntry]>::ref_from_bytes(footer_bytes).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].offset.get(), size_of::<FileHeader>() as u64);
+    assert_eq!(entries[1].time.get(), 1_500_000);
+    assert_eq!(
+        entries[1].offset.get(),
+        (size_of::<FileHeader>() + size_of::<RecordHeader>() + 3) as u64
+    );
+
+    let footer = Footer::read_from_suffix(&bytes).unwrap().1;
+    assert_eq!(footer.magic, FOOTER_MAGIC);
+    assert_eq!(footer.records.get(), 2);
+    assert_eq!(
+        footer.index_offset.get(),
+        (bytes.len() - size_of::<Footer>() - 2 * size_of::<IndexEntry>()) as u64
+    );
+}
+
+#[test]
+fn test_oversized_datagram() {
+    let start = Instant::now();
+    let mut writer = CaptureWriter::new(Vec::new(), start).unwrap();
+    assert!(
+        writer
+            .write(start, StreamKind::Video, src(), &vec![0; 70_000])
+            .is_err()
+    );
+    assert_eq!(writer.records(), 0);
+}
+
+#[test]
+fn test_recorder() {
+    let path = std::env::temp_dir().join(format!("u64viewer-test-{}.u64cap", std::process::id()));
+    let mut recorder = Recorder::new();
+    assert_eq!(recorder.path(), None);
+
+    // Datagrams are ignored while not recording
+    recorder.record(Instant::now(), StreamKind::Video, src(), &[1]);
+
+    recorder.start(&path).unwrap();
+    assert_eq!(recorder.path(), Some(path.as_path()));
+    recorder.record(Instant::now(), StreamKind::Video, src(), &[1, 2, 3, 4]);
+    assert_eq!(recorder.stop().unwrap(), Some(path.clone()));
+    assert_eq!(recorder.stop().unwrap(), None);
+
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let footer = Footer::read_from_suffix(&bytes).unwrap().1;
+    assert_eq!(footer.records.get(), 1);
+}
+
+#[test]
+fn test_recorder_bad_path() {
+    let mut recorder = Recorder::new();
+    let path = std::env::temp_dir()
+        .join("no-such-dir")
+        .join("capture.u64cap");
+    assert!(recorder.start(&path).is_err());
+    assert_eq!(recorder.path(), None);
+}
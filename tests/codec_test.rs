@@ -0,0 +1,118 @@
+use lib::codec::{
+    AudioStream, Encoding, LAST_LINE, PacketError, SAMPLES_PER_PACKET, VideoHeader, decode_audio,
+    decode_rle, decode_video, encode_rle,
+};
+
+#[test]
+fn test_video_header_layout() {
+    let header = VideoHeader::new(0x0102, 0x0304, 0x0506, 384, 4, 4);
+    assert_eq!(VideoHeader::SIZE, 12);
+    let datagram = header.encode(&[0xAA]);
+    assert_eq!(
+        datagram,
+        [
+            0x02, 0x01, 0x04, 0x03, 0x06, 0x05, 0x80, 0x01, 4, 4, 0, 0, 0xAA
+        ]
+    );
+
+    let datagram = header.last_line().encoding(Encoding::Rle).encode(&[]);
+    assert_eq!(&datagram[4..6], &[0x06, 0x85]);
+    assert_eq!(&datagram[10..12], &[1, 0]);
+}
+
+#[test]
+fn test_video_header_accessors() {
+    let header = VideoHeader::new(1, 2, 268, 384, 4, 4);
+    assert_eq!((header.seq(), header.frame(), header.width()), (1, 2, 384));
+    assert_eq!((header.lpp(), header.bits()), (4, 4));
+    assert_eq!(header.line_index(), 268);
+    assert!(!header.is_last_line());
+    assert_eq!(header.payload_size(), 768);
+    assert_eq!(header.encoding_type(), 0);
+
+    let header = header.last_line();
+    assert_eq!(header.line(), 0x010C | LAST_LINE);
+    assert_eq!(header.line_index(), 268);
+    assert!(header.is_last_line());
+    // Setting the marker twice keeps the line
+    assert_eq!(header.last_line(), header);
+}
+
+#[test]
+fn test_video_round_trip() {
+    let pixels: Vec<u8> = (0..=191).collect();
+    let header = VideoHeader::new(65_535, 9, 4, 384, 1, 4).last_line();
+    let datagram = header.encode(&pixels);
+
+    let packet = decode_video(&datagram).unwrap();
+    assert_eq!((packet.seq, packet.frame, packet.width), (65_535, 9, 384));
+    assert_eq!(packet.line_index(), 4);
+    assert!(packet.is_last_line());
+    assert_eq!(&*packet.data, pixels.as_slice());
+    assert_eq!(packet.header(), header);
+    assert_eq!(packet.encode(), datagram);
+}
+
+#[test]
+fn test_video_round_trip_rle() {
+    let mut pixels = vec![0x11; 300];
+    pixels.extend([0x22; 84]);
+    let header = VideoHeader::new(3, 4, 0, 384, 2, 4);
+    let datagram = header.encode(&pixels);
+    let packet = decode_video(&datagram).unwrap();
+
+    let rle = packet.encode_rle();
+    assert_eq!(&rle[10..12], &[1, 0]);
+    assert_eq!(&rle[12..], &[255, 0x11, 45, 0x11, 84, 0x22]);
+    let decoded = decode_video(&rle).unwrap();
+    assert_eq!(decoded.data, packet.data);
+    assert_eq!(decoded.header(), header);
+}
+
+#[test]
+fn test_encode_rle() {
+    assert_eq!(encode_rle(&[]), Vec::<u8>::new());
+    assert_eq!(encode_rle(&[1, 1, 2, 1]), [2, 1, 1, 2, 1, 1]);
+    let data = vec![7; 600];
+    let payload = encode_rle(&data);
+    assert_eq!(payload, [255, 7, 255, 7, 90, 7]);
+    assert_eq!(decode_rle(&payload, 600), Ok(data));
+}
+
+#[test]
+fn test_encoding_conversion() {
+    for encoding in [Encoding::Raw, Encoding::Rle] {
+        assert_eq!(Encoding::try_from(u16::from(encoding)), Ok(encoding));
+    }
+    assert_eq!(Encoding::try_from(7), Err(PacketError::Encoding(7)));
+}
+
+#[test]
+fn test_audio_layout() {
+    let mut samples = [[0; 2]; SAMPLES_PER_PACKET];
+    samples[0] = [0x0102, -2];
+    samples[SAMPLES_PER_PACKET - 1] = [i16::MIN, i16::MAX];
+    let datagram = AudioStream::new(0xBEEF, &samples).encode();
+
+    assert_eq!(datagram.len(), 2 + SAMPLES_PER_PACKET * 4);
+    assert_eq!(&datagram[..6], &[0xEF, 0xBE, 0x02, 0x01, 0xFE, 0xFF]);
+    assert_eq!(&datagram[datagram.len() - 4..], &[0x00, 0x80, 0xFF, 0x7F]);
+}
+
+#[test]
+fn test_audio_round_trip() {
+    let mut samples = [[0; 2]; SAMPLES_PER_PACKET];
+    for (i, pair) in (0i16..).zip(samples.iter_mut()) {
+        *pair = [i * 100, -i * 100];
+    }
+    let datagram = AudioStream::new(42, &samples).encode();
+    let stream = decode_audio(&datagram).unwrap();
+    assert_eq!(stream.seq(), 42);
+    assert_eq!(stream.samples(), samples);
+    assert_eq!(stream.encode(), datagram);
+
+    assert_eq!(
+        decode_audio(&datagram[1..]).unwrap_err(),
+        PacketError::Length(datagram.len() - 1)
+    );
+}
@@ -0,0 +1,142 @@
+use lib::audio::Concealer;
+use lib::sim::Tone;
+
+const PACKET: usize = 384; // Interleaved samples per packet
+
+fn tone(count: usize) -> Vec<[[i16; 2]; 192]> {
+    let mut tone = Tone::new(1000., 48_000.);
+    (0..count).map(|_| tone.next_packet()).collect()
+}
+
+fn floats(packet: &[[i16; 2]; 192]) -> Vec<f32> {
+    packet
+        .as_flattened()
+        .iter()
+        .map(|&s| f32::from(s) / 32768.)
+        .collect()
+}
+
+// Largest jump between neighbouring samples of a channel
+fn largest_step(samples: &[f32]) -> f32 {
+    samples
+        .windows(3)
+        .map(|w| (w[2] - w[0]).abs())
+        .fold(0., f32::max)
+}
+
+#[test]
+fn test_in_order_passes_through() {
+    let packets = tone(3);
+    let mut concealer = Concealer::new();
+    for (seq, packet) in (100..).zip(&packets) {
+        assert_eq!(concealer.push(seq, packet), floats(packet));
+    }
+    assert_eq!((concealer.lost(), concealer.late()), (0, 0));
+}
+
+#[test]
+fn test_reordered_within_window() {
+    let packets = tone(4);
+    let mut concealer = Concealer::new();
+    assert_eq!(concealer.push(0, &packets[0]).len(), PACKET);
+    // Packet 2 waits for packet 1
+    assert!(concealer.push(2, &packets[2]).is_empty());
+    let output = concealer.push(1, &packets[1]);
+    assert_eq!(output, [floats(&packets[1]), floats(&packets[2])].concat());
+    assert_eq!(concealer.push(3, &packets[3]), floats(&packets[3]));
+    assert_eq!(concealer.lost(), 0);
+}
+
+#[test]
+fn test_gap_length_from_sequence() {
+    let packets = tone(8);
+    let mut concealer = Concealer::new();
+    concealer.push(65_534, &packets[0]);
+    // Three packets lost across the wrap of the sequence number
+    assert!(concealer.push(2, &packets[4]).is_empty());
+    let output = concealer.push(3, &packets[5]);
+    assert_eq!(output.len(), 3 * PACKET + 2 * PACKET);
+    assert_eq!(concealer.lost(), 3);
+    assert_eq!(
+        output[3 * PACKET..],
+        [floats(&packets[4]), floats(&packets[5])].concat()
+    );
+}
+
+#[test]
+fn test_concealment_has_no_clicks() {
+    let packets = tone(4);
+    let mut concealer = Concealer::new();
+    let mut output = concealer.push(0, &packets[0]);
+    output.extend(concealer.push(2, &packets[2]));
+    output.extend(concealer.push(3, &packets[3]));
+    assert_eq!(output.len(), 4 * PACKET);
+
+    // Not silence, and no larger steps than the tone itself takes
+    let gap = &output[PACKET..2 * PACKET];
+    assert!(gap.iter().any(|s| s.abs() > 0.05));
+    let tone_step = largest_step(&floats(&packets[0]));
+    assert!(largest_step(&output) <= tone_step * 1.5);
+}
+
+#[test]
+fn test_long_gap_fades_to_silence() {
+    let packets = tone(2);
+    let mut concealer = Concealer::new();
+    concealer.push(0, &packets[0]);
+    concealer.push(10, &packets[1]);
+    let output = concealer.push(11, &packets[1]);
+    assert_eq!(concealer.lost(), 9);
+    assert_eq!(output.len(), 9 * PACKET + 2 * PACKET);
+    // Silent between the fade out and the fade in
+    assert!(output[PACKET..8 * PACKET].iter().all(|&s| s == 0.));
+    assert!(largest_step(&output) < 0.1);
+}
+
+#[test]
+fn test_late_and_duplicate_packets_are_dropped() {
+    let packets = tone(5);
+    let mut concealer = Concealer::new();
+    concealer.push(0, &packets[0]);
+    concealer.push(2, &packets[2]);
+    concealer.push(3, &packets[3]);
+    assert_eq!(concealer.lost(), 1);
+
+    // Packet 1 was concealed already
+    assert!(concealer.push(1, &packets[1]).is_empty());
+    assert!(concealer.push(3, &packets[3]).is_empty());
+    assert_eq!(concealer.late(), 2);
+    assert_eq!(concealer.push(4, &packets[4]).len(), PACKET);
+}
+
+#[test]
+fn test_restart_is_bridged() {
+    let packets = tone(2);
+    let mut concealer = Concealer::new();
+    concealer.push(0, &packets[0]);
+    let output = concealer.push(1000, &packets[1]);
+    // One packet of crossfade, then the new stream without waiting
+    assert_eq!(output.len(), 2 * PACKET);
+    assert_eq!(output[PACKET..], floats(&packets[1]));
+    assert_eq!(concealer.push(1001, &packets[1]).len(), PACKET);
+    // The packets in between are not counted as lost
+    assert_eq!(concealer.lost(), 0);
+}
+
+#[test]
+fn test_backward_restart_resumes() {
+    let packets = tone(6);
+    let mut concealer = Concealer::new();
+    for (seq, packet) in (5000..).zip(&packets[..4]) {
+        concealer.push(seq, packet);
+    }
+    // The sender came back counting from 0, it is played at once instead of dropped as late
+    let output = concealer.push(0, &packets[4]);
+    assert_eq!(output.len(), 2 * PACKET);
+    assert_eq!(output[PACKET..], floats(&packets[4]));
+    assert_eq!(concealer.push(1, &packets[5]), floats(&packets[5]));
+    assert_eq!(
+        (concealer.lost(), concealer.late(), concealer.restarts()),
+        (0, 0, 1)
+    );
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::unreadable_literal)] // Colors are written as 0x00RRGGBB
+
 use lib::constants::{COLORS, colors_to_u32};
 
 #[test]
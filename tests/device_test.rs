@@ -0,0 +1,42 @@
+use lib::audio::{AudioOutput, DeviceSelector, open_device};
+
+fn names() -> Vec<String> {
+    ["default", "USB Audio CODEC", "HDA Intel PCH", "USB"]
+        .map(String::from)
+        .to_vec()
+}
+
+#[test]
+fn test_parse_selector() {
+    assert_eq!("2".parse::<DeviceSelector>(), Ok(DeviceSelector::Index(2)));
+    assert_eq!(
+        " USB Audio ".parse::<DeviceSelector>(),
+        Ok(DeviceSelector::Name("USB Audio".to_string()))
+    );
+    assert!("".parse::<DeviceSelector>().is_err());
+}
+
+#[test]
+fn test_find_by_index() {
+    assert_eq!(DeviceSelector::Index(2).find(&names()), Some(2));
+    assert_eq!(DeviceSelector::Index(4).find(&names()), None);
+}
+
+#[test]
+fn test_find_by_name() {
+    let find = |name: &str| DeviceSelector::Name(name.to_string()).find(&names());
+    // An exact match wins over an earlier partial one
+    assert_eq!(find("usb"), Some(3));
+    assert_eq!(find("codec"), Some(1));
+    assert_eq!(find("hda intel pch"), Some(2));
+    assert_eq!(find("HDMI"), None);
+}
+
+#[test]
+fn test_unknown_host() {
+    let output = AudioOutput {
+        host: Some("NoSuchHost".to_string()),
+        device: None,
+    };
+    assert!(open_device(&output).is_err());
+}
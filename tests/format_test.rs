@@ -0,0 +1,99 @@
+use cpal::{SampleFormat, SupportedBufferSize, SupportedStreamConfigRange};
+use lib::audio::{rank_configs, write_frames};
+
+fn range(channels: u16, min: u32, max: u32, format: SampleFormat) -> SupportedStreamConfigRange {
+    SupportedStreamConfigRange::new(channels, min, max, SupportedBufferSize::Unknown, format)
+}
+
+fn summary(ranges: &[SupportedStreamConfigRange]) -> Vec<(u16, u32, SampleFormat)> {
+    rank_configs(ranges)
+        .iter()
+        .map(|c| (c.channels(), c.sample_rate(), c.sample_format()))
+        .collect()
+}
+
+#[test]
+fn test_prefers_stereo_float_at_48000() {
+    let ranges = [
+        range(1, 8_000, 96_000, SampleFormat::F32),
+        range(2, 8_000, 96_000, SampleFormat::I16),
+        range(2, 8_000, 96_000, SampleFormat::F32),
+        range(6, 8_000, 96_000, SampleFormat::F32),
+    ];
+    assert_eq!(
+        summary(&ranges),
+        [
+            (2, 48_000, SampleFormat::F32),
+            (2, 48_000, SampleFormat::I16),
+            (6, 48_000, SampleFormat::F32),
+            (1, 48_000, SampleFormat::F32),
+        ]
+    );
+}
+
+#[test]
+fn test_rate_choice() {
+    assert_eq!(
+        summary(&[range(2, 44_100, 44_100, SampleFormat::U16)]),
+        [(2, 44_100, SampleFormat::U16)]
+    );
+    // Closest to 48000 Hz when neither rate is supported
+    assert_eq!(
+        summary(&[
+            range(2, 88_200, 192_000, SampleFormat::F32),
+            range(2, 32_000, 32_000, SampleFormat::F32),
+        ]),
+        [
+            (2, 32_000, SampleFormat::F32),
+            (2, 88_200, SampleFormat::F32)
+        ]
+    );
+    assert_eq!(
+        summary(&[
+            range(2, 22_050, 22_050, SampleFormat::F32),
+            range(2, 44_100, 48_000, SampleFormat::F32),
+        ])[0],
+        (2, 48_000, SampleFormat::F32)
+    );
+}
+
+#[test]
+fn test_skips_unsupported_formats() {
+    let ranges = [
+        range(2, 48_000, 48_000, SampleFormat::U64),
+        range(2, 48_000, 48_000, SampleFormat::I24),
+        range(0, 48_000, 48_000, SampleFormat::F32),
+    ];
+    assert!(rank_configs(&ranges).is_empty());
+}
+
+#[test]
+fn test_write_stereo_i16() {
+    let mut data = [0i16; 4];
+    write_frames(&[0.5, -0.5, 1., -1.], &mut data, 2);
+    assert_eq!(data, [16_384, -16_384, 32_767, -32_768]);
+}
+
+#[test]
+fn test_write_unsigned() {
+    let mut data = [0u16; 2];
+    write_frames(&[0., -1.], &mut data, 2);
+    assert_eq!(data, [32_768, 0]);
+}
+
+#[test]
+fn test_downmix_to_mono() {
+    let mut data = [0f32; 2];
+    write_frames(&[0.5, 0.25, -1., 1.], &mut data, 1);
+    assert_eq!(data.map(f32::to_bits), [0.375f32, 0.].map(f32::to_bits));
+}
+
+#[test]
+fn test_multichannel_front_speakers() {
+    let mut data = [1i16; 12];
+    write_frames(&[0.5, -0.5, 0.25, -0.25], &mut data, 6);
+    assert_eq!(
+        data,
+        [16_384, -16_384, 0, 0, 0, 0, 8_192, -8_192, 0, 0, 0, 0]
+    );
+}
@@ -0,0 +1,95 @@
+use lib::{Input, SampleQueue, Sink, Statistics, SyntheticInput, TimingDetector, TimingProfile};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+fn sink(muted: bool) -> (Sink, tokio::sync::mpsc::Receiver<lib::Frame>) {
+    let (video_tx, video_rx) = tokio::sync::mpsc::channel(4);
+    let sink = Sink {
+        video_tx,
+        audio_buffer: (!muted).then(|| Arc::new(SampleQueue::new(48_000, 0))),
+        wav: None,
+        timing: Arc::new(Mutex::new(TimingDetector::new())),
+        stats: Arc::new(Mutex::new(Statistics::new())),
+    };
+    (sink, video_rx)
+}
+
+// Lines that are black all over
+fn bar_lines(frame: &lib::Frame) -> Vec<usize> {
+    (0..frame.height)
+        .filter(|&y| {
+            frame.pixels[y * frame.stride()..][..frame.stride()]
+                .iter()
+                .all(|&p| p == 0)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_synthetic_frames() {
+    let (sink, mut video_rx) = sink(true);
+    let stats = sink.stats.clone();
+    let cancel = CancellationToken::new();
+    let input: Box<dyn Input> = Box::new(SyntheticInput::new(TimingProfile::Ntsc));
+    let task = tokio::spawn(input.run(sink, cancel.clone()));
+
+    let frame = video_rx.recv().await.unwrap();
+    assert_eq!((frame.width, frame.height, frame.bits), (384, 240, 4));
+    assert!(frame.concealed.iter().all(|&c| !c));
+    // Color bars with a black bar of eight lines
+    let bar = bar_lines(&frame);
+    assert_eq!(bar.len(), 8);
+    let line = &frame.pixels[(bar[0] + 8) * frame.stride()..][..frame.stride()];
+    assert_eq!(line[0], 0x00);
+    assert_eq!(line[12], 0x11);
+    assert_eq!(line[191], 0xFF);
+
+    // The next frame has the bar one line further down
+    let frame = video_rx.recv().await.unwrap();
+    assert_eq!(bar_lines(&frame)[0], bar[0] + 1);
+
+    cancel.cancel();
+    task.await.unwrap().unwrap();
+    let snapshot = stats.lock().unwrap().snapshot();
+    assert!(snapshot.video.signal);
+    assert_eq!(snapshot.video.lost, 0);
+}
+
+#[tokio::test]
+async fn test_synthetic_tone() {
+    let (sink, _video_rx) = sink(false);
+    let audio_buffer = sink.audio_buffer.clone().unwrap();
+    let cancel = CancellationToken::new();
+    let task =
+        tokio::spawn(Box::new(SyntheticInput::new(TimingProfile::Pal)).run(sink, cancel.clone()));
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    cancel.cancel();
+    task.await.unwrap().unwrap();
+
+    assert!(audio_buffer.len() >= 384);
+    assert_eq!(audio_buffer.len() % 384, 0);
+    let mut samples = vec![0.; audio_buffer.len()];
+    assert!(audio_buffer.pop_slice(&mut samples));
+    // Both channels carry the same tone
+    assert!(
+        samples
+            .chunks(2)
+            .all(|pair| pair[0].to_bits() == pair[1].to_bits())
+    );
+    let peak = samples.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+    assert!((0.2..=0.25).contains(&peak));
+}
+
+#[tokio::test]
+async fn test_cancel_before_start() {
+    let (sink, mut video_rx) = sink(true);
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    Box::new(SyntheticInput::new(TimingProfile::Pal))
+        .run(sink, cancel)
+        .await
+        .unwrap();
+    assert!(video_rx.recv().await.is_none());
+}
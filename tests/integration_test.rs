@@ -1,5 +1,12 @@
+#![allow(clippy::cast_precision_loss)]
+
 use lib::RingBuffer;
+use lib::harness::{Harness, expected_samples};
+use lib::sim::{Tone, audio_packet, test_pattern, video_packets};
+use std::net::{IpAddr, Ipv4Addr};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
 
 #[test]
 fn test_audio_buffer_thread_safety() {
@@ -20,3 +27,208 @@ fn test_audio_buffer_thread_safety() {
     let buf = buffer.lock().expect("Failed to acquire lock on buffer");
     assert_eq!(buf.len(), 200);
 }
+
+const LINES: usize = 272;
+const STRIDE: usize = 192; // Bytes per line of 4 bit pixels
+
+// Packets of frames 0 to `count - 1` of the test pattern
+fn frames(count: u16) -> Vec<Vec<Vec<u8>>> {
+    let mut seq = 0;
+    (0..count)
+        .map(|frame| video_packets(&test_pattern(frame, LINES), frame, &mut seq))
+        .collect()
+}
+
+async fn send_all(harness: &Harness, packets: &[Vec<u8>]) {
+    for packet in packets {
+        harness.send_video(packet).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_loopback_frames() {
+    let mut harness = Harness::start().await.unwrap();
+    let frames = frames(3);
+    for packets in &frames {
+        send_all(&harness, packets).await;
+    }
+
+    // The first frame only synchronizes the assembler
+    for number in 1..3 {
+        let frame = harness.frame().await.unwrap();
+        assert_eq!((frame.width, frame.height, frame.bits), (384, LINES, 4));
+        assert_eq!(frame.pixels, test_pattern(number, LINES));
+        assert!(frame.concealed.iter().all(|&c| !c));
+    }
+
+    let stats = harness.settle(3 * 68, 0).await;
+    assert_eq!(stats.video.received, 3 * 68);
+    assert_eq!(stats.video.lost, 0);
+    assert_eq!(stats.video.source.unwrap().ip(), Ipv4Addr::LOCALHOST);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_loss() {
+    let mut harness = Harness::start().await.unwrap();
+    let mut frames = frames(3);
+    frames[2].remove(10); // Lines 40 to 43
+    for packets in &frames {
+        send_all(&harness, packets).await;
+    }
+
+    harness.frame().await.unwrap();
+    let frame = harness.frame().await.unwrap();
+    // The lost lines keep the previous frame
+    let (previous, current) = (test_pattern(1, LINES), test_pattern(2, LINES));
+    for line in 0..LINES {
+        let expected = if (40..44).contains(&line) {
+            &previous
+        } else {
+            &current
+        };
+        assert_eq!(
+            frame.pixels[line * STRIDE..][..STRIDE],
+            expected[line * STRIDE..][..STRIDE]
+        );
+        assert_eq!(frame.concealed[line], (40..44).contains(&line));
+    }
+
+    let stats = harness.settle(3 * 68 - 1, 0).await;
+    assert_eq!(stats.video.lost, 1);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_reordering() {
+    let mut harness = Harness::start().await.unwrap();
+    let mut frames = frames(2);
+    frames[1].swap(20, 21);
+    frames[1].swap(0, 5);
+    for packets in &frames {
+        send_all(&harness, packets).await;
+    }
+
+    let frame = harness.frame().await.unwrap();
+    assert_eq!(frame.pixels, test_pattern(1, LINES));
+    assert!(frame.concealed.iter().all(|&c| !c));
+
+    let stats = harness.settle(2 * 68, 0).await;
+    assert_eq!(stats.video.lost, 0);
+    // Packets 1 to 4 and 0 arrive after 5, 20 after 21
+    assert_eq!(stats.video.reordered, 6);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_truncated_packets() {
+    let mut harness = Harness::start().await.unwrap();
+    let mut frames = frames(3);
+    frames[2][30].truncate(100);
+    for packets in &frames {
+        send_all(&harness, packets).await;
+    }
+    harness.send_audio(&[0; 10]).await.unwrap();
+
+    harness.frame().await.unwrap();
+    let frame = harness.frame().await.unwrap();
+    assert_eq!(frame.concealed.iter().filter(|&&c| c).count(), 4);
+    assert!(frame.concealed[120..124].iter().all(|&c| c));
+
+    let stats = harness.settle(3 * 68, 1).await;
+    assert_eq!(stats.video.malformed, 1);
+    assert_eq!(stats.audio.malformed, 1);
+    assert_eq!(stats.audio.received, 0);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_audio() {
+    let harness = Harness::start().await.unwrap();
+    let mut tone = Tone::new(1000., 48_000.);
+    let packets: Vec<_> = (0..3).map(|_| tone.next_packet()).collect();
+    for (seq, samples) in (0..).zip(&packets) {
+        harness
+            .send_audio(&audio_packet(seq, samples))
+            .await
+            .unwrap();
+    }
+
+    let expected: Vec<f32> = packets.iter().flat_map(expected_samples).collect();
+    let samples = harness.samples(expected.len()).await;
+    assert_eq!(samples, expected);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_audio_loss() {
+    let harness = Harness::start().await.unwrap();
+    let mut tone = Tone::new(1000., 48_000.);
+    let packets: Vec<_> = (0..4).map(|_| tone.next_packet()).collect();
+    // The second packet is lost, it is given up once the fourth arrives
+    for seq in [0, 2, 3] {
+        harness
+            .send_audio(&audio_packet(seq, &packets[usize::from(seq)]))
+            .await
+            .unwrap();
+    }
+
+    let first = expected_samples(&packets[0]);
+    let last: Vec<f32> = packets[2..].iter().flat_map(expected_samples).collect();
+    let samples = harness.samples(first.len() + 384 + last.len()).await;
+    assert_eq!(samples.len(), first.len() + 384 + last.len());
+    assert_eq!(samples[..first.len()], first);
+    assert_eq!(samples[first.len() + 384..], last);
+    // The gap is filled with a fade between its neighbours instead of silence
+    let gap = &samples[first.len()..first.len() + 384];
+    assert!(gap.iter().any(|s| s.abs() > 0.01));
+    let steps = samples.windows(2).map(|w| (w[1] - w[0]).abs());
+    assert!(steps.fold(0f32, f32::max) < 0.1);
+
+    let stats = harness.settle(0, 3).await;
+    assert_eq!(stats.audio.lost, 1);
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_audio_reorder() {
+    let harness = Harness::start().await.unwrap();
+    let mut tone = Tone::new(1000., 48_000.);
+    let packets: Vec<_> = (0..4).map(|_| tone.next_packet()).collect();
+    for seq in [0, 2, 1, 3] {
+        harness
+            .send_audio(&audio_packet(seq, &packets[usize::from(seq)]))
+            .await
+            .unwrap();
+    }
+
+    // The late packet is played in its place
+    let expected: Vec<f32> = packets.iter().flat_map(expected_samples).collect();
+    let samples = harness.samples(expected.len()).await;
+    assert_eq!(samples, expected);
+
+    let stats = harness.settle(0, 4).await;
+    assert_eq!((stats.audio.lost, stats.audio.reordered), (0, 1));
+    harness.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rejected_sender_does_not_keep_signal() {
+    let timeout = Duration::from_millis(200);
+    let allowed = vec![IpAddr::from(Ipv4Addr::LOCALHOST)];
+    let harness = Harness::start_allowing(allowed, timeout).await.unwrap();
+    let packets = &frames(1)[0];
+    harness.send_video(&packets[0]).await.unwrap();
+    assert!(harness.settle(1, 0).await.video.signal);
+
+    // A sender that is not allowed keeps sending, the allowed one went quiet
+    let stray = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, 2), 0))
+        .await
+        .unwrap();
+    for packet in packets.iter().cycle().take(40) {
+        stray.send_to(packet, harness.video_addr()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(!harness.stats().video.signal);
+    harness.stop().await.unwrap();
+}
@@ -0,0 +1,144 @@
+use lib::audio::{LatencyController, latency_samples};
+use lib::{Resampler, SampleQueue, TimingProfile};
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEVICE_RATE: f64 = 48_000.;
+const CALLBACK: usize = 480; // Device frames per callback
+const RATE: f64 = 50_000.; // Stream rate for round numbers of samples
+const TARGET: Duration = Duration::from_millis(100); // 10,000 samples at `RATE`
+
+#[test]
+fn test_latency_samples() {
+    // Both channels at the rate of the machine
+    let latency = Duration::from_millis(100);
+    assert_eq!(
+        latency_samples(latency, TimingProfile::Pal.sample_rate()),
+        9_596
+    );
+    assert_eq!(
+        latency_samples(latency, TimingProfile::Ntsc.sample_rate()),
+        9_588
+    );
+    assert_eq!(latency_samples(Duration::ZERO, RATE), 0);
+}
+
+#[test]
+fn test_ratio_follows_fill() {
+    let mut controller = LatencyController::new(TARGET);
+    assert_eq!(
+        controller.update(10_000, RATE, 0.01).to_bits(),
+        1f64.to_bits()
+    );
+
+    // Play faster when the buffer is too full, slower when it runs low
+    let mut controller = LatencyController::new(TARGET);
+    assert!(controller.update(15_000, RATE, 0.01) > 1.);
+    let mut controller = LatencyController::new(TARGET);
+    assert!(controller.update(5_000, RATE, 0.01) < 1.);
+}
+
+#[test]
+fn test_ratio_changes_are_tiny() {
+    let mut controller = LatencyController::new(TARGET);
+    for _ in 0..10_000 {
+        let ratio = controller.update(48_000, RATE, 0.01);
+        assert!((ratio - 1.).abs() <= 0.002 + 1e-12);
+    }
+    for _ in 0..10_000 {
+        let ratio = controller.update(0, RATE, 0.01);
+        assert!((ratio - 1.).abs() <= 0.002 + 1e-12);
+    }
+}
+
+#[test]
+fn test_restart_keeps_drift() {
+    let mut controller = LatencyController::new(TARGET);
+    for _ in 0..1000 {
+        controller.update(12_000, RATE, 0.01);
+    }
+    let ratio = controller.ratio();
+    assert!(ratio > 1.);
+    controller.restart();
+    assert_eq!(controller.fill(), None);
+    // On target after the restart, only the settled drift is left
+    let after = controller.update(10_000, RATE, 0.01);
+    assert!(after > 1. && after < ratio);
+}
+
+#[test]
+fn test_target_follows_sample_rate() {
+    // The depth of the latency at the PAL rate is a little too much for the slower NTSC stream
+    let (pal, ntsc) = (
+        TimingProfile::Pal.sample_rate(),
+        TimingProfile::Ntsc.sample_rate(),
+    );
+    let fill = latency_samples(TARGET, pal);
+    let mut controller = LatencyController::new(TARGET);
+    assert!((controller.update(fill, pal, 0.01) - 1.).abs() < 1e-6);
+    let mut controller = LatencyController::new(TARGET);
+    assert!(controller.update(fill, ntsc, 0.01) > 1. + 1e-6);
+}
+
+// Plays `seconds` of a stream of `profile` whose clock runs `drift` faster than the sound card,
+// with the packets of 192 frames arriving in bursts. Returns the averaged fill at the end.
+fn simulate(
+    queue: &Arc<SampleQueue>,
+    latency: Duration,
+    profile: TimingProfile,
+    drift: f64,
+    seconds: usize,
+) -> f64 {
+    let stream_rate = profile.sample_rate();
+    let mut resampler = Resampler::new(queue.clone(), stream_rate, DEVICE_RATE);
+    let mut controller = LatencyController::new(latency);
+    let packet = [0.; 384];
+    let mut sent = 0.;
+    let callbacks = seconds * 100;
+    let mut data = [0.; 2 * CALLBACK];
+    for callback in 1..=callbacks {
+        #[allow(clippy::cast_precision_loss)]
+        let now = callback as f64 * CALLBACK as f64 / DEVICE_RATE;
+        // Packets arrive four at a time
+        while sent + 4. * 192. <= now * stream_rate * (1. + drift) {
+            for _ in 0..4 {
+                queue.push_slice(&packet);
+            }
+            sent += 4. * 192.;
+        }
+
+        let ratio = if queue.is_starved() {
+            controller.restart();
+            controller.ratio()
+        } else {
+            #[allow(clippy::cast_precision_loss)]
+            let elapsed = CALLBACK as f64 / DEVICE_RATE;
+            controller.update(queue.len(), stream_rate, elapsed)
+        };
+        resampler.set_stream_rate(stream_rate * ratio);
+        resampler.fill(&mut data);
+    }
+    controller.fill().unwrap()
+}
+
+#[test]
+fn test_compensates_drift() {
+    let latency = Duration::from_millis(50);
+    for profile in [TimingProfile::Pal, TimingProfile::Ntsc] {
+        for drift in [-0.000_5, 0., 0.000_5] {
+            let target = latency_samples(latency, profile.sample_rate());
+            let queue = Arc::new(SampleQueue::new(48_000, target));
+            let fill = simulate(&queue, latency, profile, drift, 60);
+
+            assert_eq!(queue.overruns(), 0, "{profile:?} drift {drift}");
+            // At most the start of playback
+            assert!(queue.underruns() <= 1, "{profile:?} drift {drift}");
+            #[allow(clippy::cast_precision_loss)]
+            let target = target as f64;
+            assert!(
+                (fill - target).abs() < target / 10.,
+                "{profile:?} drift {drift}: {fill}"
+            );
+        }
+    }
+}
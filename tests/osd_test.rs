@@ -0,0 +1,113 @@
+use lib::video::{CHAR_SIZE, draw_no_signal, draw_osd, draw_text, glyph, osd_lines};
+use lib::{StatsSnapshot, TimingProfile};
+use std::net::SocketAddr;
+
+const FG: u32 = 0x00FF_FFFF;
+const BG: u32 = 0x0000_0001;
+
+#[test]
+fn test_glyph_lookup() {
+    assert_eq!(
+        glyph('A'),
+        &[0x18, 0x3C, 0x66, 0x7E, 0x66, 0x66, 0x66, 0x00]
+    );
+    assert_eq!(glyph('a'), glyph('A'));
+    assert_eq!(glyph(' '), &[0; 8]);
+    assert_eq!(
+        glyph('0'),
+        &[0x3C, 0x66, 0x6E, 0x76, 0x66, 0x66, 0x3C, 0x00]
+    );
+    assert_eq!(glyph('~'), glyph('?'));
+    assert_eq!(glyph('é'), glyph('?'));
+}
+
+#[test]
+fn test_draw_text_pixels() {
+    let width = 16;
+    let mut buffer = vec![0u32; width * CHAR_SIZE];
+    draw_text(&mut buffer, width, (0, 0), "I.", (FG, BG));
+
+    // Top row of `I` is 0x3C
+    let row: Vec<bool> = buffer[..8].iter().map(|&p| p == FG).collect();
+    assert_eq!(row, [false, false, true, true, true, true, false, false]);
+    // Every pixel of the two characters is drawn
+    assert!(buffer.iter().all(|&p| p == FG || p == BG));
+}
+
+#[test]
+fn test_draw_text_is_clipped() {
+    let width = 12;
+    let height = 4;
+    let mut buffer = vec![0u32; width * height];
+    draw_text(&mut buffer, width, (8, 0), "WW", (FG, BG));
+
+    // Only the first four columns of the first glyph fit
+    for line in buffer.chunks(width) {
+        assert!(line[..8].iter().all(|&p| p == 0));
+        assert!(line[8..].iter().all(|&p| p == FG || p == BG));
+    }
+}
+
+#[test]
+fn test_draw_text_outside_buffer() {
+    let mut buffer = vec![0u32; 64];
+    draw_text(&mut buffer, 8, (100, 100), "HELLO", (FG, BG));
+    assert!(buffer.iter().all(|&p| p == 0));
+}
+
+#[test]
+fn test_draw_osd_leaves_rest_of_frame() {
+    let width = 384;
+    let mut buffer = vec![0u32; width * 272];
+    draw_osd(&mut buffer, width, &["FPS 50.1".to_string()]);
+
+    // Three text rows starting at the margin, ten characters wide
+    assert!(buffer[..8 * width].iter().all(|&p| p == 0));
+    let row = &buffer[8 * width..9 * width];
+    assert!(row[..8].iter().all(|&p| p == 0));
+    assert!(row[8..88].iter().all(|&p| p != 0));
+    assert!(row[88..].iter().all(|&p| p == 0));
+    assert!(buffer[32 * width..].iter().all(|&p| p == 0));
+}
+
+#[test]
+fn test_osd_lines() {
+    let mut stats = StatsSnapshot::default();
+    stats.video.frame_rate = 50.12;
+    stats.video.received = 990;
+    stats.video.lost = 10;
+    stats.video.source = Some("192.168.1.64:1234".parse::<SocketAddr>().unwrap());
+    stats.audio_fill = 12_000;
+    stats.audio_underruns = 3;
+
+    let lines = osd_lines(&stats, (384, 272, 4), Some(TimingProfile::Pal));
+    assert!(lines.contains(&"FPS 50.1".to_string()));
+    assert!(lines.contains(&"VIDEO 384X272 4BPP PAL".to_string()));
+    assert!(lines.contains(&"VIDEO LOSS 1.00%".to_string()));
+    assert!(lines.contains(&"AUDIO BUFFER 12000 UNDERRUNS 3".to_string()));
+    assert!(lines.contains(&"SOURCE 192.168.1.64".to_string()));
+}
+
+#[test]
+fn test_osd_lines_without_signal() {
+    let lines = osd_lines(&StatsSnapshot::default(), (384, 272, 4), None);
+    assert!(lines.contains(&"VIDEO 384X272 4BPP -".to_string()));
+    assert!(lines.contains(&"SOURCE -".to_string()));
+}
+
+#[test]
+fn test_draw_no_signal() {
+    let (width, height) = (384, 272);
+    let mut buffer = vec![0u32; width * height];
+    draw_no_signal(&mut buffer, width, height);
+
+    let blue = buffer[0];
+    assert_ne!(blue, 0);
+    // The message is centered, nine characters on the middle text row
+    let text: Vec<usize> = (0..width * height).filter(|&i| buffer[i] != blue).collect();
+    let (first, last) = (text[0], text[text.len() - 1]);
+    assert!((132..136).contains(&(first / width)));
+    assert!((132..140).contains(&(last / width)));
+    assert!(first % width >= 156);
+    assert!(last % width < 156 + 9 * CHAR_SIZE);
+}
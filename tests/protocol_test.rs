@@ -0,0 +1,193 @@
+use lib::network::{DropCounts, Encoding, PacketError, decode_audio, decode_rle, decode_video};
+
+fn video_datagram(width: u16, lpp: u8, bits: u8, encoding: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&7u16.to_le_bytes()); // seq
+    packet.extend_from_slice(&3u16.to_le_bytes()); // frame
+    packet.extend_from_slice(&4u16.to_le_bytes()); // line
+    packet.extend_from_slice(&width.to_le_bytes());
+    packet.push(lpp);
+    packet.push(bits);
+    packet.extend_from_slice(&encoding.to_le_bytes());
+    packet.extend_from_slice(payload);
+    packet
+}
+
+fn video_packet(line: u16, encoding: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = video_datagram(384, 4, 4, encoding, payload);
+    packet[4..6].copy_from_slice(&line.to_le_bytes());
+    packet
+}
+
+#[test]
+fn test_encoding_from_header_value() {
+    assert_eq!(Encoding::try_from(0), Ok(Encoding::Raw));
+    assert_eq!(Encoding::try_from(1), Ok(Encoding::Rle));
+    assert_eq!(Encoding::try_from(2), Err(PacketError::Encoding(2)));
+}
+
+#[test]
+fn test_decode_raw_packet() {
+    let payload: Vec<u8> = (0..=255u8).cycle().take(768).collect();
+    let datagram = video_packet(0x8000 | 0x010C, 0, &payload);
+    let packet = decode_video(&datagram).unwrap();
+
+    assert_eq!(packet.seq, 7);
+    assert_eq!(packet.frame, 3);
+    assert_eq!(packet.line, 0x8000 | 0x010C);
+    assert_eq!(packet.width, 384);
+    assert_eq!(packet.lpp, 4);
+    assert_eq!(packet.bits, 4);
+    assert_eq!(&packet.data[..], &payload[..]);
+}
+
+#[test]
+fn test_decode_raw_packet_too_short() {
+    let datagram = video_packet(0, 0, &[0u8; 100]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Length(112)
+    );
+}
+
+#[test]
+fn test_decode_raw_packet_too_long() {
+    let datagram = video_packet(0, 0, &[0u8; 1000]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Length(1012)
+    );
+}
+
+#[test]
+fn test_decode_header_too_short() {
+    assert_eq!(decode_video(&[0u8; 8]).unwrap_err(), PacketError::Length(8));
+}
+
+#[test]
+fn test_decode_unsupported_geometry() {
+    let datagram = video_datagram(0, 4, 4, 0, &[]);
+    assert_eq!(decode_video(&datagram).unwrap_err(), PacketError::Width(0));
+
+    let datagram = video_datagram(383, 4, 4, 0, &[0u8; 766]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Width(383)
+    );
+
+    let datagram = video_datagram(4096, 1, 4, 0, &[0u8; 2048]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Width(4096)
+    );
+
+    let datagram = video_datagram(384, 0, 4, 0, &[]);
+    assert_eq!(decode_video(&datagram).unwrap_err(), PacketError::Lpp(0));
+
+    let datagram = video_datagram(8, 17, 4, 0, &[0u8; 68]);
+    assert_eq!(decode_video(&datagram).unwrap_err(), PacketError::Lpp(17));
+
+    let datagram = video_packet(0x7FFC, 0, &[0u8; 768]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Line(0x7FFC)
+    );
+    // The last lines of the largest frame are still accepted
+    assert!(decode_video(&video_packet(0x83FC, 0, &[0u8; 768])).is_ok());
+
+    let datagram = video_datagram(384, 4, 7, 0, &[0u8; 768]);
+    assert_eq!(decode_video(&datagram).unwrap_err(), PacketError::Bits(7));
+}
+
+#[test]
+fn test_decode_header_driven_geometry() {
+    let datagram = video_datagram(320, 8, 4, 0, &[0u8; 1280]);
+    let packet = decode_video(&datagram).unwrap();
+    assert_eq!((packet.width, packet.lpp, packet.bits), (320, 8, 4));
+    assert_eq!(packet.data.len(), 1280);
+
+    let datagram = video_datagram(384, 2, 8, 0, &[0u8; 768]);
+    let packet = decode_video(&datagram).unwrap();
+    assert_eq!(packet.bits, 8);
+    assert_eq!(packet.data.len(), 768);
+}
+
+#[test]
+fn test_decode_rle_packet() {
+    // Three bands of border, background and border colour
+    let datagram = video_packet(4, 1, &[200, 0xEE, 255, 0x66, 255, 0x66, 58, 0xEE]);
+    let packet = decode_video(&datagram).unwrap();
+
+    assert_eq!(packet.line, 4);
+    assert_eq!(packet.data.len(), 768);
+    assert!(packet.data[..200].iter().all(|&b| b == 0xEE));
+    assert!(packet.data[200..710].iter().all(|&b| b == 0x66));
+    assert!(packet.data[710..].iter().all(|&b| b == 0xEE));
+}
+
+#[test]
+fn test_decode_unknown_encoding() {
+    let datagram = video_packet(0, 5, &[0u8; 768]);
+    assert_eq!(
+        decode_video(&datagram).unwrap_err(),
+        PacketError::Encoding(5)
+    );
+}
+
+#[test]
+fn test_decode_invalid_rle_packet() {
+    let datagram = video_packet(0, 1, &[10, 0x11]);
+    assert_eq!(decode_video(&datagram).unwrap_err(), PacketError::Rle);
+}
+
+#[test]
+fn test_decode_audio_packet_length() {
+    assert!(decode_audio(&[0u8; 770]).is_ok());
+    assert_eq!(
+        decode_audio(&[0u8; 769]).unwrap_err(),
+        PacketError::Length(769)
+    );
+    assert_eq!(
+        decode_audio(&[0u8; 780]).unwrap_err(),
+        PacketError::Length(780)
+    );
+}
+
+#[test]
+fn test_drop_counts_per_reason() {
+    let mut drops = DropCounts::default();
+    assert_eq!(drops.count(&PacketError::Length(10)), 1);
+    assert_eq!(drops.count(&PacketError::Length(20)), 2);
+    assert_eq!(drops.count(&PacketError::Bits(8)), 1);
+
+    assert_eq!(drops.get("length"), 2);
+    assert_eq!(drops.get("bits"), 1);
+    assert_eq!(drops.get("width"), 0);
+    assert_eq!(drops.total(), 3);
+}
+
+#[test]
+fn test_rle_single_runs() {
+    let data = decode_rle(&[1, 0x12, 2, 0x34, 1, 0x56], 4).unwrap();
+    assert_eq!(data, vec![0x12, 0x34, 0x34, 0x56]);
+}
+
+#[test]
+fn test_rle_too_short() {
+    assert!(decode_rle(&[3, 0x11], 4).is_err());
+}
+
+#[test]
+fn test_rle_overflow() {
+    assert!(decode_rle(&[3, 0x11, 3, 0x22], 4).is_err());
+}
+
+#[test]
+fn test_rle_zero_length_run() {
+    assert!(decode_rle(&[0, 0x11, 4, 0x22], 4).is_err());
+}
+
+#[test]
+fn test_rle_dangling_count() {
+    assert!(decode_rle(&[4, 0x11, 1], 4).is_err());
+}
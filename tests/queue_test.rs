@@ -0,0 +1,163 @@
+use lib::{SampleQueue, TimingProfile};
+use std::sync::Arc;
+
+// Samples are moved as they are, so they compare bit for bit
+fn bits(samples: &[f32]) -> Vec<u32> {
+    samples.iter().map(|s| s.to_bits()).collect()
+}
+
+#[test]
+fn test_push_and_pop_slices() {
+    let queue = SampleQueue::new(8, 0);
+    assert!(queue.is_empty());
+    assert_eq!(queue.push_slice(&[1., 2., 3.]), 3);
+    assert_eq!(queue.len(), 3);
+
+    let mut out = [0.; 2];
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[1., 2.]));
+    assert_eq!(queue.len(), 1);
+}
+
+#[test]
+fn test_wraps_around() {
+    let queue = SampleQueue::new(4, 0);
+    let mut out = [0.; 3];
+    for i in 0..10u8 {
+        let start = f32::from(i) * 3.;
+        let samples = [start, start + 1., start + 2.];
+        assert_eq!(queue.push_slice(&samples), 3);
+        assert!(queue.pop_slice(&mut out));
+        assert_eq!(bits(&out), bits(&samples));
+    }
+}
+
+#[test]
+fn test_overruns_drop_newest() {
+    let queue = SampleQueue::new(4, 0);
+    assert_eq!(queue.push_slice(&[1., 2., 3.]), 3);
+    assert_eq!(queue.push_slice(&[4., 5., 6.]), 1);
+    assert_eq!(queue.overruns(), 2);
+
+    let mut out = [0.; 4];
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[1., 2., 3., 4.]));
+}
+
+#[test]
+fn test_pop_waits_for_min_fill() {
+    let queue = SampleQueue::new(100, 4);
+    queue.push_slice(&[1., 2., 3.]);
+    let mut out = [9.; 2];
+    assert!(!queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[0., 0.]));
+    assert_eq!(queue.len(), 3);
+
+    // Once prebuffered the queue plays until it runs dry
+    queue.push_slice(&[4.]);
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[1., 2.]));
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[3., 4.]));
+    assert!(!queue.is_starved());
+
+    // And prebuffers again after that
+    assert!(!queue.pop_slice(&mut out));
+    assert!(queue.is_starved());
+    queue.push_slice(&[5., 6.]);
+    assert!(!queue.pop_slice(&mut out));
+    queue.push_slice(&[7., 8.]);
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[5., 6.]));
+}
+
+#[test]
+fn test_underruns_count_playback_running_dry() {
+    let queue = SampleQueue::new(100, 0);
+    let mut out = [0.; 2];
+
+    // Prebuffering is not an underrun
+    assert!(!queue.pop_slice(&mut out));
+    assert_eq!(queue.underruns(), 0);
+
+    queue.push_slice(&[1., 2.]);
+    assert!(queue.pop_slice(&mut out));
+    assert!(!queue.pop_slice(&mut out));
+    assert!(!queue.pop_slice(&mut out));
+    assert_eq!(queue.underruns(), 1);
+
+    queue.push_slice(&[3., 4.]);
+    assert!(queue.pop_slice(&mut out));
+    assert!(!queue.pop_slice(&mut out));
+    assert_eq!(queue.underruns(), 2);
+}
+
+#[test]
+fn test_clear() {
+    let queue = SampleQueue::new(4, 0);
+    queue.push_slice(&[1., 2., 3.]);
+    queue.clear();
+    assert!(queue.is_empty());
+
+    queue.push_slice(&[4.]);
+    assert_eq!(queue.len(), 1);
+    let mut out = [0.];
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[4.]));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_sample_rate() {
+    let queue = SampleQueue::new(4, 0);
+    let rate = |queue: &SampleQueue| queue.sample_rate().to_bits();
+    assert_eq!(rate(&queue), TimingProfile::Pal.sample_rate().to_bits());
+    queue.set_sample_rate(TimingProfile::Ntsc.sample_rate());
+    assert_eq!(rate(&queue), TimingProfile::Ntsc.sample_rate().to_bits());
+}
+
+#[test]
+fn test_push_after_clear() {
+    let queue = SampleQueue::new(4, 0);
+    assert_eq!(queue.push_slice(&[1., 2., 3., 4.]), 4);
+    queue.clear();
+
+    // The cleared samples make room before the consumer has skipped them
+    assert_eq!(queue.push_slice(&[5., 6., 7., 8.]), 4);
+    assert_eq!(queue.overruns(), 0);
+    assert_eq!(queue.len(), 4);
+    let mut out = [0.; 4];
+    assert!(queue.pop_slice(&mut out));
+    assert_eq!(bits(&out), bits(&[5., 6., 7., 8.]));
+}
+
+#[test]
+fn test_threads_keep_order() {
+    const COUNT: u16 = 50_000;
+    let queue = Arc::new(SampleQueue::new(64, 0));
+    let producer = {
+        let queue = queue.clone();
+        std::thread::spawn(move || {
+            let samples: Vec<f32> = (0..COUNT).map(f32::from).collect();
+            let mut pushed = 0;
+            while pushed < samples.len() {
+                let end = (pushed + 10).min(samples.len());
+                pushed += queue.push_slice(&samples[pushed..end]);
+            }
+        })
+    };
+
+    let mut expected = 0;
+    let mut out = [0.; 2];
+    while expected < COUNT {
+        if queue.pop_slice(&mut out) {
+            assert_eq!(
+                bits(&out),
+                bits(&[f32::from(expected), f32::from(expected + 1)])
+            );
+            expected += 2;
+        }
+    }
+    producer.join().unwrap();
+    assert!(queue.is_empty());
+}
@@ -0,0 +1,84 @@
+use lib::video::{draw_frame, visible_rows};
+use lib::{COLORS, Frame, TimingProfile, colors_to_u32};
+
+fn color(index: usize) -> u32 {
+    colors_to_u32(COLORS[index])
+}
+
+#[test]
+fn test_draw_frame_4bpp() {
+    let frame = Frame {
+        width: 4,
+        height: 2,
+        bits: 4,
+        pixels: vec![0x21, 0x43, 0x65, 0x87],
+        concealed: vec![false, false],
+    };
+    let mut buffer = vec![0; 8];
+    draw_frame(&mut buffer, &frame, &COLORS, false);
+    // The low nibble holds the leftmost pixel
+    let expected: Vec<u32> = (1..=8).map(color).collect();
+    assert_eq!(buffer, expected);
+}
+
+#[test]
+fn test_draw_frame_8bpp_tinted() {
+    let frame = Frame {
+        width: 2,
+        height: 2,
+        bits: 8,
+        pixels: vec![1, 0x11, 1, 1],
+        concealed: vec![false, true],
+    };
+    let mut buffer = vec![0; 4];
+    draw_frame(&mut buffer, &frame, &COLORS, true);
+    // Only the low nibble is used
+    assert_eq!(&buffer[..2], &[color(1), color(1)]);
+    assert_eq!(buffer[2], buffer[3]);
+    assert_ne!(buffer[2], color(1));
+}
+
+#[test]
+fn test_draw_frame_mismatched_geometry() {
+    // Claims more lines than there are pixels, flags and room in the buffer
+    let frame = Frame {
+        width: 8,
+        height: 100,
+        bits: 4,
+        pixels: vec![0x11; 12],
+        concealed: vec![false; 5],
+    };
+    let mut buffer = vec![0; 8 * 2];
+    draw_frame(&mut buffer, &frame, &COLORS, false);
+    assert!(buffer.iter().all(|&p| p == color(1)));
+
+    let mut buffer = vec![0; 8 * 4];
+    draw_frame(&mut buffer, &frame, &COLORS, false);
+    assert!(buffer[..24].iter().all(|&p| p == color(1)));
+    assert!(buffer[24..].iter().all(|&p| p == 0));
+}
+
+#[test]
+fn test_draw_frame_empty() {
+    let frame = Frame {
+        width: 0,
+        height: 3,
+        bits: 0,
+        pixels: vec![1, 2, 3],
+        concealed: vec![false; 3],
+    };
+    let mut buffer = vec![0; 3];
+    draw_frame(&mut buffer, &frame, &COLORS, false);
+    draw_frame(&mut [], &frame, &COLORS, false);
+}
+
+#[test]
+fn test_visible_rows() {
+    // An NTSC frame renders 240 rows, also when the frame carries more border lines
+    assert_eq!(visible_rows(240, Some(TimingProfile::Ntsc)).len(), 240);
+    assert_eq!(visible_rows(272, Some(TimingProfile::Ntsc)), 16..256);
+    assert_eq!(visible_rows(272, Some(TimingProfile::Pal)), 0..272);
+    // Frames smaller than the visible area and an unknown machine are shown whole
+    assert_eq!(visible_rows(200, Some(TimingProfile::Pal)), 0..200);
+    assert_eq!(visible_rows(272, None), 0..272);
+}
@@ -0,0 +1,375 @@
+use lib::capture::{CaptureReader, CaptureWriter, StreamKind};
+use lib::network::NetworkConfig;
+use lib::replay::{
+    LINKTYPE_ETHERNET, LINKTYPE_LINUX_SLL2, LINKTYPE_NULL, LINKTYPE_RAW, PcapReader, Speed,
+    open_capture, replay_tasks, udp_datagram,
+};
+use lib::{ReplayConfig, Statistics, TimingDetector};
+use std::io::Cursor;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const VIDEO_PORT: u16 = 11_000;
+const AUDIO_PORT: u16 = 11_001;
+
+fn ipv4_udp(dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let total = u16::try_from(28 + payload.len()).unwrap();
+    let mut packet = vec![0x45, 0];
+    packet.extend_from_slice(&total.to_be_bytes());
+    packet.extend_from_slice(&[0, 0, 0x40, 0, 64, 17, 0, 0]); // Don't fragment, TTL, UDP
+    packet.extend_from_slice(&[192, 168, 1, 64, 239, 0, 1, 64]);
+    packet.extend_from_slice(&50_000u16.to_be_bytes());
+    packet.extend_from_slice(&dst_port.to_be_bytes());
+    packet.extend_from_slice(&u16::try_from(8 + payload.len()).unwrap().to_be_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    packet.extend_from_slice(payload);
+    packet
+}
+
+fn ethernet(ip: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x01, 0x00, 0x5E, 0, 1, 64, 0x02, 0, 0, 0, 0, 1, 0x08, 0x00];
+    frame.extend_from_slice(ip);
+    frame
+}
+
+// Little-endian pcap with microsecond timestamps
+fn pcap(frames: &[(u32, u32, Vec<u8>)]) -> Vec<u8> {
+    let mut file = Vec::new();
+    file.extend_from_slice(&0xA1B2_C3D4u32.to_le_bytes());
+    file.extend_from_slice(&2u16.to_le_bytes());
+    file.extend_from_slice(&4u16.to_le_bytes());
+    file.extend_from_slice(&[0; 8]);
+    file.extend_from_slice(&65_535u32.to_le_bytes());
+    file.extend_from_slice(&u32::from(LINKTYPE_ETHERNET).to_le_bytes());
+    for (seconds, micros, frame) in frames {
+        let len = u32::try_from(frame.len()).unwrap();
+        file.extend_from_slice(&seconds.to_le_bytes());
+        file.extend_from_slice(&micros.to_le_bytes());
+        file.extend_from_slice(&len.to_le_bytes());
+        file.extend_from_slice(&len.to_le_bytes());
+        file.extend_from_slice(frame);
+    }
+    file
+}
+
+fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(12 + body.len().next_multiple_of(4)).unwrap();
+    let mut block = Vec::new();
+    block.extend_from_slice(&block_type.to_be_bytes());
+    block.extend_from_slice(&len.to_be_bytes());
+    block.extend_from_slice(body);
+    block.resize(usize::try_from(len).unwrap() - 4, 0);
+    block.extend_from_slice(&len.to_be_bytes());
+    block
+}
+
+// Big-endian pcapng with nanosecond timestamps
+fn pcapng(frames: &[(u64, Vec<u8>)]) -> Vec<u8> {
+    let mut section = Vec::new();
+    section.extend_from_slice(&0x1A2B_3C4Du32.to_be_bytes());
+    section.extend_from_slice(&[0, 1, 0, 0]);
+    section.extend_from_slice(&[0xFF; 8]);
+    let mut file = block(0x0A0D_0D0A, &section);
+
+    let mut interface = Vec::new();
+    interface.extend_from_slice(&LINKTYPE_ETHERNET.to_be_bytes());
+    interface.extend_from_slice(&[0, 0, 0, 0, 0xFF, 0xFF]);
+    interface.extend_from_slice(&[0, 9, 0, 1, 9, 0, 0, 0]); // if_tsresol: nanoseconds
+    interface.extend_from_slice(&[0, 0, 0, 0]);
+    file.extend(block(1, &interface));
+
+    for (nanos, frame) in frames {
+        let len = u32::try_from(frame.len()).unwrap();
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&0u32.to_be_bytes());
+        packet.extend_from_slice(&u32::try_from(nanos >> 32).unwrap().to_be_bytes());
+        packet.extend_from_slice(&u32::try_from(nanos & 0xFFFF_FFFF).unwrap().to_be_bytes());
+        packet.extend_from_slice(&len.to_be_bytes());
+        packet.extend_from_slice(&len.to_be_bytes());
+        packet.extend_from_slice(frame);
+        file.extend(block(6, &packet));
+    }
+    file
+}
+
+fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("u64viewer-{}-{name}", std::process::id()));
+    std::fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn video_datagram(seq: u16, frame: u16, line: u16) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&seq.to_le_bytes());
+    packet.extend_from_slice(&frame.to_le_bytes());
+    packet.extend_from_slice(&line.to_le_bytes());
+    packet.extend_from_slice(&8u16.to_le_bytes()); // width
+    packet.push(1); // lpp
+    packet.push(8); // bits
+    packet.extend_from_slice(&0u16.to_le_bytes());
+    packet.extend_from_slice(&[3; 8]);
+    packet
+}
+
+#[test]
+fn test_parse_speed() {
+    assert_eq!("1".parse(), Ok(Speed::Factor(1.)));
+    assert_eq!("2x".parse(), Ok(Speed::Factor(2.)));
+    assert_eq!("0.5".parse(), Ok(Speed::Factor(0.5)));
+    assert_eq!("MAX".parse(), Ok(Speed::Max));
+    assert!("0".parse::<Speed>().is_err());
+    assert!("-1".parse::<Speed>().is_err());
+    assert!("fast".parse::<Speed>().is_err());
+}
+
+#[test]
+fn test_read_pcap() {
+    let frame = ethernet(&ipv4_udp(VIDEO_PORT, &[1, 2, 3]));
+    let file = pcap(&[(10, 500, frame.clone()), (11, 0, frame.clone())]);
+    let packets: Vec<_> = PcapReader::new(Cursor::new(file))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].time, Duration::new(10, 500_000));
+    assert_eq!(packets[1].time, Duration::from_secs(11));
+    assert_eq!(packets[0].link_type, LINKTYPE_ETHERNET);
+    assert_eq!(packets[0].data, frame);
+}
+
+#[test]
+fn test_truncated_pcap_ends_early() {
+    let frame = ethernet(&ipv4_udp(VIDEO_PORT, &[1, 2, 3]));
+    let mut file = pcap(&[(10, 0, frame.clone()), (11, 0, frame)]);
+    file.truncate(file.len() - 5);
+    let packets: Vec<_> = PcapReader::new(Cursor::new(file)).unwrap().collect();
+    assert_eq!(packets.len(), 1);
+    assert!(packets[0].is_ok());
+}
+
+#[test]
+fn test_read_pcapng() {
+    let frame = ethernet(&ipv4_udp(AUDIO_PORT, &[9; 5]));
+    let file = pcapng(&[(1_500_000_000_123, frame.clone())]);
+    let packets: Vec<_> = PcapReader::new(Cursor::new(file))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].time, Duration::new(1500, 123));
+    assert_eq!(packets[0].link_type, LINKTYPE_ETHERNET);
+    assert_eq!(packets[0].data, frame);
+}
+
+#[test]
+fn test_not_a_capture() {
+    assert!(PcapReader::new(Cursor::new(b"GIF89a..".to_vec())).is_err());
+}
+
+#[test]
+fn test_udp_datagram() {
+    let ip = ipv4_udp(VIDEO_PORT, &[1, 2, 3]);
+    let frame = ethernet(&ip);
+    let datagram = udp_datagram(LINKTYPE_ETHERNET, &frame).unwrap();
+    assert_eq!(datagram.src.ip(), &Ipv4Addr::new(192, 168, 1, 64));
+    assert_eq!(datagram.src.port(), 50_000);
+    assert_eq!(datagram.dst.ip(), &Ipv4Addr::new(239, 0, 1, 64));
+    assert_eq!(datagram.dst.port(), VIDEO_PORT);
+    assert_eq!(datagram.payload, &[1, 2, 3]);
+
+    assert_eq!(udp_datagram(LINKTYPE_RAW, &ip), Some(datagram));
+
+    let mut null = 2u32.to_le_bytes().to_vec();
+    null.extend_from_slice(&ip);
+    assert_eq!(udp_datagram(LINKTYPE_NULL, &null), Some(datagram));
+
+    let mut sll2 = vec![0x08, 0x00];
+    sll2.resize(20, 0);
+    sll2.extend_from_slice(&ip);
+    assert_eq!(udp_datagram(LINKTYPE_LINUX_SLL2, &sll2), Some(datagram));
+}
+
+#[test]
+fn test_udp_datagram_with_vlan_tag() {
+    let ip = ipv4_udp(VIDEO_PORT, &[1, 2, 3]);
+    let mut frame = ethernet(&ip);
+    frame.splice(12..12, [0x81, 0x00, 0x00, 0x05]);
+    assert_eq!(
+        udp_datagram(LINKTYPE_ETHERNET, &frame).unwrap().payload,
+        &[1, 2, 3]
+    );
+}
+
+#[test]
+fn test_udp_datagram_skips_others() {
+    let mut fragment = ipv4_udp(VIDEO_PORT, &[1, 2, 3]);
+    fragment[6] = 0x20; // More fragments
+    assert_eq!(udp_datagram(LINKTYPE_RAW, &fragment), None);
+
+    let mut tcp = ipv4_udp(VIDEO_PORT, &[1, 2, 3]);
+    tcp[9] = 6;
+    assert_eq!(udp_datagram(LINKTYPE_RAW, &tcp), None);
+
+    let mut ipv6 = ethernet(&ipv4_udp(VIDEO_PORT, &[1, 2, 3]));
+    ipv6[12..14].copy_from_slice(&[0x86, 0xDD]);
+    assert_eq!(udp_datagram(LINKTYPE_ETHERNET, &ipv6), None);
+
+    assert_eq!(udp_datagram(LINKTYPE_RAW, &[0x45, 0, 0]), None);
+    assert_eq!(udp_datagram(147, &[0; 64]), None);
+}
+
+#[test]
+fn test_open_pcap_by_port() {
+    let file = pcap(&[
+        (1, 0, ethernet(&ipv4_udp(VIDEO_PORT, &[1]))),
+        (1, 10, ethernet(&ipv4_udp(53, &[2]))),
+        (1, 20, ethernet(&ipv4_udp(AUDIO_PORT, &[3]))),
+    ]);
+    let path = temp_file("ports.pcap", &file);
+    let datagrams: Vec<_> = open_capture(&path, VIDEO_PORT, AUDIO_PORT)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(datagrams.len(), 2);
+    assert_eq!(datagrams[0].kind, StreamKind::Video);
+    assert_eq!(datagrams[0].data, [1]);
+    assert_eq!(datagrams[1].kind, StreamKind::Audio);
+    assert_eq!(datagrams[1].time, Duration::new(1, 20_000));
+    assert_eq!(
+        datagrams[1].src,
+        "192.168.1.64:50000".parse::<SocketAddr>().unwrap()
+    );
+}
+
+#[test]
+fn test_open_capture_file() {
+    let start = Instant::now();
+    let src = "192.168.1.64:50000".parse().unwrap();
+    let mut writer = CaptureWriter::new(Vec::new(), start).unwrap();
+    writer
+        .write(start, StreamKind::Audio, src, &[7, 7])
+        .unwrap();
+    writer
+        .write(
+            start + Duration::from_millis(20),
+            StreamKind::Video,
+            src,
+            &[8],
+        )
+        .unwrap();
+    let path = temp_file("session.u64cap", &writer.finish().unwrap());
+
+    // Ports don't matter, the stream is stored in the capture file
+    let datagrams: Vec<_> = open_capture(&path, 1, 2)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(datagrams.len(), 2);
+    assert_eq!(datagrams[0].kind, StreamKind::Audio);
+    assert_eq!(datagrams[1].kind, StreamKind::Video);
+    assert_eq!(datagrams[1].time, Duration::from_millis(20));
+    assert_eq!(datagrams[1].src, src);
+}
+
+#[test]
+fn test_capture_reader_seek() {
+    let start = Instant::now();
+    let src = "192.168.1.64:50000".parse().unwrap();
+    let mut writer = CaptureWriter::new(Vec::new(), start).unwrap();
+    for i in 0..5u8 {
+        let now = start + Duration::from_millis(700) * u32::from(i);
+        writer.write(now, StreamKind::Video, src, &[i]).unwrap();
+    }
+    let mut reader = CaptureReader::new(Cursor::new(writer.finish().unwrap())).unwrap();
+    assert_eq!(reader.index().len(), 3);
+
+    // Records at 0.0, 0.7, 1.4, 2.1 and 2.8 seconds, index entries at 0.0, 1.4 and 2.1
+    reader.seek(Duration::from_secs(2)).unwrap();
+    let (header, data) = reader.next_record().unwrap().unwrap();
+    assert_eq!(header.time.get(), 1_400_000);
+    assert_eq!(data, [2]);
+    assert_eq!(reader.count(), 2);
+}
+
+#[test]
+fn test_unfinished_capture_file() {
+    let start = Instant::now();
+    let src = "192.168.1.64:50000".parse().unwrap();
+    let mut writer = CaptureWriter::new(Vec::new(), start).unwrap();
+    writer
+        .write(start, StreamKind::Video, src, &[1, 2, 3])
+        .unwrap();
+    writer
+        .write(start, StreamKind::Video, src, &[4, 5, 6])
+        .unwrap();
+
+    // Without the index and footer, cut off halfway through the second record
+    let mut bytes = writer.finish().unwrap();
+    bytes.truncate(bytes.len() - 24 - 2 * 16 - 2);
+    let reader = CaptureReader::new(Cursor::new(bytes)).unwrap();
+    assert!(reader.index().is_empty());
+    let records: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].1, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_replay_through_decoders() {
+    // Two frames of two lines, the first one only synchronizes the assembler
+    let mut frames = Vec::new();
+    for (i, (frame, line)) in [(0, 0), (0, 0x8001), (1, 0), (1, 0x8001)]
+        .iter()
+        .enumerate()
+    {
+        let ip = ipv4_udp(
+            VIDEO_PORT,
+            &video_datagram(u16::try_from(i).unwrap(), *frame, *line),
+        );
+        frames.push((1, u32::try_from(i).unwrap() * 10_000, ethernet(&ip)));
+    }
+    let path = temp_file("replay.pcap", &pcap(&frames));
+
+    let timing = Arc::new(Mutex::new(TimingDetector::new()));
+    let stats = Arc::new(Mutex::new(Statistics::new()));
+    let (video_tx, mut video_rx) = tokio::sync::mpsc::channel(4);
+    let config = NetworkConfig {
+        video_maddr: Ipv4Addr::new(239, 0, 1, 64),
+        audio_maddr: Ipv4Addr::new(239, 0, 1, 65),
+        video_port: VIDEO_PORT,
+        audio_port: AUDIO_PORT,
+        timeout: Duration::from_secs(2),
+        interface: None,
+        recv_buffer: 0,
+        allowed_sources: Vec::new(),
+        demux: false,
+        source: None,
+    };
+    let replay = ReplayConfig {
+        path: path.clone(),
+        speed: Speed::Max,
+    };
+    tokio::spawn(replay_tasks(
+        replay,
+        config,
+        video_tx,
+        None,
+        timing,
+        stats.clone(),
+    ));
+
+    let frame = video_rx.recv().await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((frame.width, frame.height, frame.bits), (8, 2, 8));
+    assert!(frame.pixels.iter().all(|&p| p == 3));
+    let snapshot = stats.lock().unwrap().snapshot();
+    assert_eq!(snapshot.video.received, 4);
+    assert_eq!(snapshot.video.lost, 0);
+}
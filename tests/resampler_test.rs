@@ -0,0 +1,91 @@
+use lib::{Resampler, SampleQueue};
+use std::f64::consts::TAU;
+use std::sync::Arc;
+
+// Interleaved stereo sine of `frequency` Hz at `rate`, the right channel inverted
+fn sine(frequency: f64, rate: f64, frames: usize) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|i| {
+            #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+            let sample = (TAU * frequency * i as f64 / rate).sin() as f32 * 0.5;
+            [sample, -sample]
+        })
+        .collect()
+}
+
+// Upward zero crossings of the left channel per second
+fn frequency(samples: &[f32], rate: f64) -> f64 {
+    let left: Vec<f32> = samples.iter().step_by(2).copied().collect();
+    let crossings = left.windows(2).filter(|w| w[0] < 0. && w[1] >= 0.).count();
+    #[allow(clippy::cast_precision_loss)]
+    let seconds = left.len() as f64 / rate;
+    #[allow(clippy::cast_precision_loss)]
+    let crossings = crossings as f64;
+    crossings / seconds
+}
+
+#[test]
+fn test_same_rate_passes_through() {
+    let buffer = Arc::new(SampleQueue::new(1000, 0));
+    buffer.push_slice(&[0.1, -0.1, 0.2, -0.2, 0.3, -0.3, 0.4, -0.4]);
+    let mut resampler = Resampler::new(buffer, 48_000., 48_000.);
+
+    let mut data = [1.; 12];
+    resampler.fill(&mut data);
+    // Two frames of delay for the interpolation, then the stream as it is
+    let expected = [0., 0., 0., 0., 0.1, -0.1, 0.2, -0.2, 0.3, -0.3, 0.4, -0.4];
+    for (sample, expected) in data.iter().zip(expected) {
+        assert!((sample - expected).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_keeps_pitch() {
+    let stream_rate = 47_982.887;
+    for device_rate in [48_000., 44_100., 96_000.] {
+        let buffer = Arc::new(SampleQueue::new(200_000, 0));
+        buffer.push_slice(&sine(1000., stream_rate, 48_000));
+        let mut resampler = Resampler::new(buffer, stream_rate, device_rate);
+
+        let mut data = vec![0.; 2 * 40_000];
+        resampler.fill(&mut data);
+        let measured = frequency(&data, device_rate);
+        assert!(
+            (measured - 1000.).abs() < 5.,
+            "{device_rate} Hz: {measured}"
+        );
+        // The channels stay apart
+        assert!(data.chunks(2).all(|pair| (pair[0] + pair[1]).abs() < 1e-6));
+    }
+}
+
+#[test]
+fn test_consumes_stream_rate() {
+    let buffer = Arc::new(SampleQueue::new(200_000, 0));
+    buffer.push_slice(&vec![0.; 2 * 48_000]);
+    let mut resampler = Resampler::new(buffer.clone(), 48_000., 44_100.);
+
+    let mut data = vec![0.; 2 * 44_100];
+    resampler.fill(&mut data);
+    // One second at the device rate takes one second of the stream
+    let consumed = 48_000 - buffer.len() / 2;
+    assert!(consumed.abs_diff(48_000) <= 3);
+}
+
+#[test]
+fn test_set_stream_rate() {
+    let buffer = Arc::new(SampleQueue::new(1000, 0));
+    let mut resampler = Resampler::new(buffer, 47_982.887, 44_100.);
+    resampler.set_stream_rate(47_940.341);
+    assert!((resampler.stream_rate() - 47_940.341).abs() < 1e-6);
+    assert_eq!(resampler.device_rate().to_bits(), 44_100f64.to_bits());
+}
+
+#[test]
+fn test_silence_when_starved() {
+    let buffer = Arc::new(SampleQueue::new(1000, 0));
+    let mut resampler = Resampler::new(buffer, 47_982.887, 44_100.);
+    let mut data = [1.; 64];
+    resampler.fill(&mut data);
+    assert!(data.iter().all(|&s| s.abs() < f32::EPSILON));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::cast_precision_loss, clippy::float_cmp)]
+
 use lib::RingBuffer;
 
 #[test]
@@ -98,3 +100,45 @@ fn test_fifo_ordering() {
     assert_eq!(buffer.pop(), 3);
     assert_eq!(buffer.pop(), 4);
 }
+
+#[test]
+fn test_overruns_count_dropped_samples() {
+    let mut buffer = RingBuffer::new(5, 0);
+    for i in 0..8 {
+        buffer.push(i);
+    }
+    assert_eq!(buffer.overruns(), 3);
+}
+
+#[test]
+fn test_underruns_count_playback_running_dry() {
+    let mut buffer = RingBuffer::new(100, 2);
+
+    // Prebuffering is not an underrun
+    assert_eq!(buffer.pop(), 0);
+    assert_eq!(buffer.underruns(), 0);
+
+    buffer.push(1);
+    buffer.push(2);
+    assert_eq!(buffer.pop(), 1);
+    assert_eq!(buffer.pop(), 0);
+    assert_eq!(buffer.pop(), 0);
+    assert_eq!(buffer.underruns(), 1);
+
+    buffer.push(3);
+    buffer.push(4);
+    assert_eq!(buffer.pop(), 2);
+    assert_eq!(buffer.pop(), 3);
+    assert_eq!(buffer.pop(), 0);
+    assert_eq!(buffer.underruns(), 2);
+}
+
+#[test]
+fn test_drain() {
+    let mut buffer = RingBuffer::new(10, 5);
+    for i in 1..4 {
+        buffer.push(i);
+    }
+    assert_eq!(buffer.drain().collect::<Vec<_>>(), [1, 2, 3]);
+    assert!(buffer.is_empty());
+}
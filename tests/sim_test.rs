@@ -0,0 +1,297 @@
+use lib::network::{decode_audio, decode_video};
+use lib::sim::{
+    Impairment, Scheduler, Tone, audio_packet, load_png, load_wav, nearest_color, quantize,
+    test_pattern, video_packets,
+};
+use lib::{FrameAssembler, HEIGHT, WIDTH};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("u64viewer-{}-{name}", std::process::id()))
+}
+
+#[test]
+fn test_video_packets() {
+    let pixels = test_pattern(0, HEIGHT);
+    let mut seq = 65_534;
+    let packets = video_packets(&pixels, 7, &mut seq);
+    assert_eq!(packets.len(), HEIGHT / 4);
+    assert_eq!(seq, 66);
+
+    for (i, packet) in packets.iter().enumerate() {
+        let packet = decode_video(packet).unwrap();
+        assert_eq!(
+            packet.seq,
+            65_534u16.wrapping_add(u16::try_from(i).unwrap())
+        );
+        assert_eq!(packet.frame, 7);
+        assert_eq!(packet.line & 0x7FFF, u16::try_from(i * 4).unwrap());
+        assert_eq!(packet.line & 0x8000 != 0, i == packets.len() - 1);
+        assert_eq!((packet.width, packet.lpp, packet.bits), (384, 4, 4));
+    }
+}
+
+#[test]
+fn test_video_packets_assemble() {
+    let mut assembler = FrameAssembler::new();
+    let mut seq = 0;
+    let mut frame = None;
+    for number in 0..2 {
+        let pixels = test_pattern(number, HEIGHT);
+        for packet in video_packets(&pixels, number, &mut seq) {
+            frame = assembler.push(&decode_video(&packet).unwrap()).or(frame);
+        }
+    }
+    let frame = frame.unwrap();
+    assert_eq!((frame.width, frame.height, frame.bits), (WIDTH, HEIGHT, 4));
+    assert_eq!(frame.pixels, test_pattern(1, HEIGHT));
+}
+
+#[test]
+fn test_test_pattern() {
+    let stride = WIDTH / 2;
+    let pixels = test_pattern(0, 240);
+    assert_eq!(pixels.len(), stride * 240);
+    // Black bar over the first eight lines, 16 color bars below
+    assert!(pixels[..8 * stride].iter().all(|&p| p == 0));
+    let line = &pixels[8 * stride..9 * stride];
+    assert_eq!(line[0], 0x00);
+    assert_eq!(line[12], 0x11);
+    assert_eq!(line[stride - 1], 0xFF);
+
+    // The bar wraps around at the bottom
+    let pixels = test_pattern(236, 240);
+    assert!(pixels[236 * stride..].iter().all(|&p| p == 0));
+    assert!(pixels[..4 * stride].iter().all(|&p| p == 0));
+    assert!(pixels[4 * stride..236 * stride].iter().any(|&p| p != 0));
+}
+
+#[test]
+fn test_audio_packet() {
+    let mut tone = Tone::new(1000., 48_000.);
+    let samples = tone.next_packet();
+    let stream = decode_audio(&audio_packet(513, &samples)).unwrap();
+    assert_eq!(stream.seq(), 513);
+    assert_eq!(stream.samples(), samples);
+}
+
+#[test]
+fn test_tone() {
+    let mut tone = Tone::new(1000., 48_000.);
+    let first = tone.next_packet();
+    let second = tone.next_packet();
+    assert_eq!(first[0], [0, 0]);
+    assert!(first.iter().all(|[left, right]| left == right));
+    // 48 samples per period, the phase continues in the next packet
+    assert_eq!(first[12][0], 8191);
+    assert_eq!(second[12][0], first[12][0]);
+    assert_eq!(second[0][0], first[0][0]);
+}
+
+#[test]
+fn test_nearest_color() {
+    assert_eq!(nearest_color([0, 0, 0]), 0);
+    assert_eq!(nearest_color([255, 255, 255]), 1);
+    assert_eq!(nearest_color([0x8D, 0x2F, 0x34]), 2);
+    assert_eq!(nearest_color([0x70, 0x70, 0xFF]), 14);
+}
+
+#[test]
+fn test_quantize_scales() {
+    // 2 by 2 image, white and black on top, red and gray below
+    let rgb = [
+        [255, 255, 255],
+        [0, 0, 0],
+        [0x8D, 0x2F, 0x34],
+        [0x7B, 0x7B, 0x7B],
+    ];
+    let pixels = quantize(&rgb, 2, 2, 4);
+    let stride = WIDTH / 2;
+    assert_eq!(pixels.len(), 4 * stride);
+    assert_eq!(pixels[0], 0x11);
+    assert_eq!(pixels[stride - 1], 0x00);
+    assert_eq!(pixels[2 * stride], 0x22);
+    assert_eq!(pixels[4 * stride - 1], 0xCC);
+}
+
+#[test]
+fn test_load_png() {
+    let path = temp_path("image.png");
+    {
+        let file = std::fs::File::create(&path).unwrap();
+        let mut encoder = png::Encoder::new(file, 4, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[0, 0, 0, 255, 255, 255, 0x6D, 0x6A, 0xEF, 0xEF, 0xEF, 0x5D])
+            .unwrap();
+    }
+    let pixels = load_png(&path, 2);
+    std::fs::remove_file(&path).unwrap();
+
+    let pixels = pixels.unwrap();
+    assert_eq!(pixels.len(), WIDTH);
+    let stride = WIDTH / 2;
+    assert_eq!(&pixels[..stride], &pixels[stride..]);
+    assert_eq!(pixels[0], 0x00);
+    assert_eq!(pixels[48], 0x11);
+    assert_eq!(pixels[96], 0xEE);
+    assert_eq!(pixels[stride - 1], 0x77);
+}
+
+#[test]
+fn test_load_png_invalid() {
+    let path = temp_path("invalid.png");
+    std::fs::write(&path, b"not a png").unwrap();
+    let result = load_png(&path, HEIGHT);
+    std::fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+    assert!(load_png(&temp_path("missing.png"), HEIGHT).is_err());
+}
+
+#[test]
+fn test_load_wav() {
+    let path = temp_path("mono.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 44_100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for sample in [0i16, 1000, -32768, 32767] {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    let wav = load_wav(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let (samples, rate) = wav.unwrap();
+    assert_eq!(rate, 44_100);
+    assert_eq!(
+        samples,
+        [[0, 0], [1000, 1000], [-32768, -32768], [32767, 32767]]
+    );
+}
+
+#[test]
+fn test_load_wav_float_stereo() {
+    let path = temp_path("stereo.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 48_000,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for sample in [0.5f32, -0.5, 2.0, -2.0] {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    let wav = load_wav(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let (samples, _) = wav.unwrap();
+    assert_eq!(samples, [[16384, -16384], [32767, -32768]]);
+}
+
+fn drain(scheduler: &mut Scheduler<u32>, now: Instant) -> Vec<u32> {
+    scheduler.flush(now);
+    iter_pop(scheduler, now + Duration::from_secs(1))
+}
+
+fn iter_pop(scheduler: &mut Scheduler<u32>, now: Instant) -> Vec<u32> {
+    std::iter::from_fn(|| scheduler.pop(now)).collect()
+}
+
+#[test]
+fn test_scheduler_without_impairment() {
+    let now = Instant::now();
+    let mut scheduler = Scheduler::new(Impairment::default(), 0);
+    for i in 0..10 {
+        scheduler.push(now + Duration::from_millis(u64::from(i)), i);
+    }
+    assert_eq!(scheduler.next_due(), Some(now));
+    assert_eq!(
+        iter_pop(&mut scheduler, now + Duration::from_millis(4)),
+        [0, 1, 2, 3, 4]
+    );
+    assert_eq!(drain(&mut scheduler, now), [5, 6, 7, 8, 9]);
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn test_scheduler_loss() {
+    let now = Instant::now();
+    let impairment = Impairment {
+        loss: 1.,
+        ..Impairment::default()
+    };
+    let mut scheduler = Scheduler::new(impairment, 0);
+    for i in 0..10 {
+        scheduler.push(now, i);
+    }
+    assert!(scheduler.is_empty());
+
+    let impairment = Impairment {
+        loss: 0.5,
+        ..Impairment::default()
+    };
+    let mut scheduler = Scheduler::new(impairment, 1);
+    for i in 0..1000 {
+        scheduler.push(now, i);
+    }
+    let received = drain(&mut scheduler, now);
+    assert!((400..600).contains(&received.len()));
+    assert!(received.is_sorted());
+}
+
+#[test]
+fn test_scheduler_reorder() {
+    let now = Instant::now();
+    let impairment = Impairment {
+        reorder: 1.,
+        ..Impairment::default()
+    };
+    let mut scheduler = Scheduler::new(impairment, 0);
+    for i in 0..5 {
+        scheduler.push(now, i);
+    }
+    // Every held packet is sent after the next one, the last one when flushed
+    assert_eq!(drain(&mut scheduler, now), [1, 0, 3, 2, 4]);
+}
+
+#[test]
+fn test_scheduler_jitter() {
+    let now = Instant::now();
+    let impairment = Impairment {
+        jitter: Duration::from_millis(10),
+        ..Impairment::default()
+    };
+    let mut scheduler = Scheduler::new(impairment, 0);
+    for i in 0..100 {
+        scheduler.push(now, i);
+    }
+    // Nothing is due before the packet with the smallest delay
+    let first = scheduler.next_due().unwrap();
+    assert!(first > now && first < now + Duration::from_millis(10));
+    assert_eq!(
+        scheduler.pop(first.checked_sub(Duration::from_nanos(1)).unwrap()),
+        None
+    );
+    assert!(scheduler.pop(now + Duration::from_millis(10)).is_some());
+
+    // The same seed gives the same order
+    let order = |seed| {
+        let mut scheduler = Scheduler::new(impairment, seed);
+        for i in 0..100 {
+            scheduler.push(now, i);
+        }
+        drain(&mut scheduler, now)
+    };
+    assert_eq!(order(5), order(5));
+    assert_ne!(order(5), order(6));
+    assert!(!order(5).is_sorted());
+}
@@ -0,0 +1,52 @@
+use lib::network::{Interface, Membership, bind_multicast};
+use std::net::Ipv4Addr;
+
+const GROUP: Ipv4Addr = Ipv4Addr::new(239, 0, 1, 64);
+const LOOPBACK: Interface = Interface::Address(Ipv4Addr::LOCALHOST);
+
+#[test]
+fn test_parse_interface() {
+    assert_eq!(
+        "192.168.1.10".parse::<Interface>(),
+        Ok(Interface::Address(Ipv4Addr::new(192, 168, 1, 10)))
+    );
+    assert_eq!(
+        " eth0 ".parse::<Interface>(),
+        Ok(Interface::Name("eth0".to_string()))
+    );
+    assert!("".parse::<Interface>().is_err());
+}
+
+#[test]
+fn test_unknown_interface_name() {
+    let interface = Interface::Name("no-such-nic0".to_string());
+    assert!(Membership::new(GROUP, Some(&interface)).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_interface_name() {
+    let interface = Interface::Name("lo".to_string());
+    let membership = Membership::new(GROUP, Some(&interface)).unwrap();
+    assert_eq!(membership.group(), GROUP);
+}
+
+#[tokio::test]
+async fn test_viewers_share_port() {
+    let membership = Membership::new(GROUP, Some(&LOOPBACK)).unwrap();
+    let first = bind_multicast(&membership, 0, 65_536).unwrap();
+    let port = first.local_addr().unwrap().port();
+
+    let second = bind_multicast(&membership, port, 65_536).unwrap();
+    assert_eq!(second.local_addr().unwrap().port(), port);
+}
+
+#[tokio::test]
+async fn test_rejoin() {
+    let membership = Membership::new(GROUP, Some(&LOOPBACK)).unwrap();
+    let socket = bind_multicast(&membership, 0, 65_536).unwrap();
+
+    membership.leave(&socket).unwrap();
+    membership.join(&socket).unwrap();
+    assert!(membership.join(&socket).is_err());
+}
@@ -0,0 +1,81 @@
+use lib::network::{SourceFilter, Verdict};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+fn ip(addr: &str) -> IpAddr {
+    addr.parse().unwrap()
+}
+
+#[test]
+fn test_any_source_by_default() {
+    let mut filter = SourceFilter::new(Vec::new(), false, None, TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Selected);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Selected);
+
+    // Without demultiplexing all sources share one state
+    assert_eq!(filter.key(ip("10.0.0.1")), filter.key(ip("10.0.0.2")));
+}
+
+#[test]
+fn test_allowed_sources() {
+    let mut filter = SourceFilter::new(vec![ip("10.0.0.1")], false, None, TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Selected);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Rejected);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Rejected);
+}
+
+#[test]
+fn test_demux_locks_onto_first_source() {
+    let mut filter = SourceFilter::new(Vec::new(), true, None, TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.selected(), None);
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Selected);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Other);
+    assert_eq!(filter.selected(), Some(ip("10.0.0.1")));
+    assert_ne!(filter.key(ip("10.0.0.1")), filter.key(ip("10.0.0.2")));
+
+    // Packets keep the lock alive
+    let later = now + Duration::from_secs(1);
+    assert_eq!(filter.check(ip("10.0.0.1"), later), Verdict::Selected);
+    assert_eq!(
+        filter.check(ip("10.0.0.2"), later + Duration::from_secs(1)),
+        Verdict::Other
+    );
+}
+
+#[test]
+fn test_demux_switches_after_timeout() {
+    let mut filter = SourceFilter::new(Vec::new(), true, None, TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Selected);
+
+    let later = now + TIMEOUT;
+    assert_eq!(filter.check(ip("10.0.0.2"), later), Verdict::Selected);
+    assert_eq!(filter.check(ip("10.0.0.1"), later), Verdict::Other);
+    assert_eq!(filter.selected(), Some(ip("10.0.0.2")));
+}
+
+#[test]
+fn test_chosen_source() {
+    let mut filter = SourceFilter::new(Vec::new(), false, Some(ip("10.0.0.2")), TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.selected(), Some(ip("10.0.0.2")));
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Other);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Selected);
+
+    // A chosen source is never replaced
+    let later = now + TIMEOUT * 10;
+    assert_eq!(filter.check(ip("10.0.0.1"), later), Verdict::Other);
+}
+
+#[test]
+fn test_demux_only_allowed_sources() {
+    let mut filter = SourceFilter::new(vec![ip("10.0.0.2")], true, None, TIMEOUT);
+    let now = Instant::now();
+    assert_eq!(filter.check(ip("10.0.0.1"), now), Verdict::Rejected);
+    assert_eq!(filter.check(ip("10.0.0.2"), now), Verdict::Selected);
+}
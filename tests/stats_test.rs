@@ -0,0 +1,148 @@
+use lib::network::PacketError;
+use lib::{Statistics, StreamStats};
+use std::time::{Duration, Instant};
+
+fn feed(stats: &mut StreamStats, seqs: &[u16]) {
+    let now = Instant::now();
+    for &seq in seqs {
+        stats.packet(now, seq);
+    }
+}
+
+#[test]
+fn test_in_order_packets() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[1, 2, 3, 4]);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.received, 4);
+    assert_eq!(snapshot.lost, 0);
+    assert_eq!(snapshot.duplicate, 0);
+    assert_eq!(snapshot.reordered, 0);
+}
+
+#[test]
+fn test_lost_packets() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[1, 2, 5, 6, 10]);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.received, 5);
+    assert_eq!(snapshot.lost, 5);
+    assert!((snapshot.loss() - 0.5).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_reordered_packet_is_not_lost() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[1, 3, 2, 4]);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.lost, 0);
+    assert_eq!(snapshot.reordered, 1);
+    assert_eq!(snapshot.duplicate, 0);
+}
+
+#[test]
+fn test_duplicate_packets() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[1, 2, 2, 3, 1]);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.duplicate, 2);
+    assert_eq!(snapshot.reordered, 0);
+    assert_eq!(snapshot.lost, 0);
+}
+
+#[test]
+fn test_sequence_wraps_around() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[u16::MAX - 1, u16::MAX, 0, 1]);
+    assert_eq!(stats.snapshot().lost, 0);
+}
+
+#[test]
+fn test_stream_restart_is_not_loss() {
+    let mut stats = StreamStats::new();
+    feed(&mut stats, &[20_000, 20_001, 0, 1]);
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.lost, 0);
+    assert_eq!(snapshot.reordered, 0);
+}
+
+#[test]
+fn test_malformed_packets() {
+    let mut stats = StreamStats::new();
+    assert_eq!(stats.malformed(&PacketError::Length(3)), 1);
+    assert_eq!(stats.malformed(&PacketError::Rle), 1);
+    assert_eq!(stats.malformed(&PacketError::Length(5)), 2);
+
+    assert_eq!(stats.snapshot().malformed, 3);
+    assert_eq!(stats.drops().get("length"), 2);
+}
+
+#[test]
+fn test_jitter_and_rates() {
+    let mut stats = StreamStats::new();
+    let start = Instant::now();
+    for i in 0..200u16 {
+        // Alternate between 8 ms and 12 ms intervals
+        let at = start + Duration::from_millis(u64::from(i) * 10 + u64::from(i % 2) * 2);
+        stats.packet(at, i);
+        if i % 4 == 0 {
+            stats.frame(at);
+        }
+    }
+
+    let snapshot = stats.snapshot();
+    let jitter = snapshot.jitter.as_secs_f64();
+    assert!(jitter > 0.001 && jitter < 0.003, "jitter {jitter}");
+    assert!((snapshot.packet_rate - 100.).abs() < 5.);
+    assert!((snapshot.frame_rate - 25.).abs() < 5.);
+}
+
+#[test]
+fn test_statistics_snapshot() {
+    let mut stats = Statistics::new();
+    feed(&mut stats.video, &[1, 2, 4]);
+    feed(&mut stats.audio, &[7]);
+    stats.audio_buffer(1234, 2, 10);
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.video.received, 3);
+    assert_eq!(snapshot.video.lost, 1);
+    assert_eq!(snapshot.audio.received, 1);
+    assert_eq!(snapshot.audio_fill, 1234);
+    assert_eq!(snapshot.audio_underruns, 2);
+    assert_eq!(snapshot.audio_overruns, 10);
+}
+
+#[test]
+fn test_source_address() {
+    let mut stats = StreamStats::new();
+    assert_eq!(stats.snapshot().source, None);
+
+    let src = "192.168.1.64:50000".parse().unwrap();
+    stats.source(src);
+    assert_eq!(stats.snapshot().source, Some(src));
+}
+
+#[test]
+fn test_signal_lost() {
+    let mut stats = StreamStats::new();
+    assert!(!stats.snapshot().signal);
+    assert!(!stats.signal_lost());
+
+    feed(&mut stats, &[1, 2, 3]);
+    assert!(stats.snapshot().signal);
+    assert!(stats.signal_lost());
+    assert!(!stats.snapshot().signal);
+
+    // The restarted stream is not counted as loss or reordering
+    feed(&mut stats, &[500, 501]);
+    let snapshot = stats.snapshot();
+    assert!(snapshot.signal);
+    assert_eq!(snapshot.received, 5);
+    assert_eq!(snapshot.lost, 0);
+    assert_eq!(snapshot.reordered, 0);
+}
@@ -0,0 +1,132 @@
+use lib::{TimingDetector, TimingProfile};
+use std::time::{Duration, Instant};
+
+fn feed_frames(detector: &mut TimingDetector, start: Instant, rate: f64, lines: usize) {
+    let period = Duration::from_secs_f64(1. / rate);
+    for i in 0..=150 {
+        detector.frame(start + period * i, lines);
+    }
+}
+
+fn feed_audio(detector: &mut TimingDetector, start: Instant, rate: f64, packets: u16) {
+    let period = Duration::from_secs_f64(192. / rate);
+    for seq in 0..packets {
+        detector.audio_packet(start + period * u32::from(seq), seq);
+    }
+}
+
+#[test]
+fn test_no_profile_without_measurements() {
+    let detector = TimingDetector::new();
+    assert_eq!(detector.profile(), None);
+    assert_eq!(detector.frame_rate(), None);
+    assert_eq!(detector.sample_rate(), None);
+}
+
+#[test]
+fn test_detect_pal() {
+    let mut detector = TimingDetector::new();
+    feed_frames(&mut detector, Instant::now(), 50.124_5, 272);
+
+    let rate = detector.frame_rate().unwrap();
+    assert!((rate - 50.124_5).abs() < 0.1);
+    assert_eq!(detector.lines(), Some(272));
+    assert_eq!(detector.profile(), Some(TimingProfile::Pal));
+}
+
+#[test]
+fn test_detect_ntsc() {
+    let mut detector = TimingDetector::new();
+    feed_frames(&mut detector, Instant::now(), 59.826_1, 240);
+
+    let rate = detector.frame_rate().unwrap();
+    assert!((rate - 59.826_1).abs() < 0.1);
+    assert_eq!(detector.profile(), Some(TimingProfile::Ntsc));
+}
+
+#[test]
+fn test_profile_from_lines_before_rate_is_known() {
+    let mut detector = TimingDetector::new();
+    detector.frame(Instant::now(), 240);
+    assert_eq!(detector.frame_rate(), None);
+    assert_eq!(detector.profile(), Some(TimingProfile::Ntsc));
+
+    detector.frame(Instant::now(), 272);
+    assert_eq!(detector.profile(), Some(TimingProfile::Pal));
+}
+
+#[test]
+fn test_frame_gap_restarts_measurement() {
+    let mut detector = TimingDetector::new();
+    let start = Instant::now();
+    detector.frame(start, 272);
+    detector.frame(start + Duration::from_secs(5), 272);
+    assert_eq!(detector.frame_rate(), None);
+}
+
+#[test]
+fn test_measure_sample_rate() {
+    let mut detector = TimingDetector::new();
+    feed_audio(&mut detector, Instant::now(), 47_982.887, 1000);
+
+    let rate = detector.sample_rate().unwrap();
+    assert!((rate - 47_982.887).abs() < 1.);
+}
+
+#[test]
+fn test_sample_rate_counts_lost_packets() {
+    let mut detector = TimingDetector::new();
+    let start = Instant::now();
+    let period = Duration::from_secs_f64(192. / 47_940.341);
+    for seq in (0..1000u16).filter(|seq| seq % 10 != 3) {
+        detector.audio_packet(start + period * u32::from(seq), seq);
+    }
+
+    let rate = detector.sample_rate().unwrap();
+    assert!((rate - 47_940.341).abs() < 1.);
+}
+
+#[test]
+fn test_sample_rate_needs_enough_data() {
+    let mut detector = TimingDetector::new();
+    feed_audio(&mut detector, Instant::now(), 47_982.887, 100);
+    assert_eq!(detector.sample_rate(), None);
+}
+
+#[test]
+fn test_stream_rate() {
+    let mut detector = TimingDetector::new();
+    assert_eq!(
+        detector.stream_rate().to_bits(),
+        TimingProfile::Pal.sample_rate().to_bits()
+    );
+
+    detector.frame(Instant::now(), 240);
+    assert_eq!(
+        detector.stream_rate().to_bits(),
+        TimingProfile::Ntsc.sample_rate().to_bits()
+    );
+
+    feed_audio(&mut detector, Instant::now(), 47_950., 1000);
+    assert!((detector.stream_rate() - 47_950.).abs() < 1.);
+}
+
+#[test]
+fn test_stream_rate_ignores_implausible_measurement() {
+    let mut detector = TimingDetector::new();
+    // Packets arriving in bursts after a stall
+    feed_audio(&mut detector, Instant::now(), 60_000., 1000);
+    assert!(detector.sample_rate().is_some());
+    assert_eq!(
+        detector.stream_rate().to_bits(),
+        TimingProfile::Pal.sample_rate().to_bits()
+    );
+}
+
+#[test]
+fn test_profile_constants() {
+    assert_eq!(TimingProfile::Pal.visible_lines(), 272);
+    assert_eq!(TimingProfile::Ntsc.visible_lines(), 240);
+    assert!(TimingProfile::Pal.sample_rate() > TimingProfile::Ntsc.sample_rate());
+    assert!(TimingProfile::Pal.frame_period() > TimingProfile::Ntsc.frame_period());
+}
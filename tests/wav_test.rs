@@ -0,0 +1,231 @@
+use lib::TimingProfile;
+use lib::audio::{WavFormat, WavRecorder};
+use lib::harness::{Harness, expected_samples};
+use lib::sim::{Tone, audio_packet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("u64viewer-{}-{name}", std::process::id()))
+}
+
+fn bits(samples: &[f32]) -> Vec<u32> {
+    samples.iter().map(|s| s.to_bits()).collect()
+}
+
+fn packets(count: usize) -> Vec<Vec<f32>> {
+    let mut tone = Tone::new(1000., 48_000.);
+    (0..count)
+        .map(|_| expected_samples(&tone.next_packet()))
+        .collect()
+}
+
+fn read_i16(path: &PathBuf) -> (hound::WavSpec, Vec<i16>) {
+    let mut reader = hound::WavReader::open(path).unwrap();
+    let samples = reader.samples::<i16>().collect::<Result<_, _>>().unwrap();
+    (reader.spec(), samples)
+}
+
+#[test]
+fn test_wav_format() {
+    assert_eq!("pcm16".parse(), Ok(WavFormat::Pcm16));
+    assert_eq!("16".parse(), Ok(WavFormat::Pcm16));
+    assert_eq!("Float".parse(), Ok(WavFormat::Float));
+    assert_eq!("f32".parse(), Ok(WavFormat::Float));
+    assert!("pcm24".parse::<WavFormat>().is_err());
+    assert_eq!(WavFormat::default(), WavFormat::Pcm16);
+}
+
+#[test]
+fn test_not_recording() {
+    let mut recorder = WavRecorder::new();
+    recorder.write(Instant::now(), None, &[0.5, -0.5], false);
+    assert_eq!(recorder.path(), None);
+    assert_eq!(recorder.stop(), Ok(Vec::new()));
+}
+
+#[test]
+fn test_record_pcm16() {
+    let path = temp_path("record.wav");
+    let packets = packets(3);
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    assert_eq!(recorder.path(), Some(path.as_path()));
+    let start = Instant::now();
+    for (i, packet) in (0u32..).zip(&packets) {
+        recorder.write(
+            start + Duration::from_millis(4 * u64::from(i)),
+            Some(TimingProfile::Pal),
+            packet,
+            i == 0,
+        );
+    }
+    assert_eq!(recorder.stop(), Ok(vec![path.clone()]));
+    assert_eq!(recorder.path(), None);
+
+    let (spec, samples) = read_i16(&path);
+    assert_eq!((spec.channels, spec.bits_per_sample), (2, 16));
+    assert_eq!(spec.sample_rate, 47_983);
+    // The samples came from 16-bit packets, so they are written back exactly
+    #[allow(clippy::cast_possible_truncation)]
+    let expected: Vec<i16> = packets
+        .iter()
+        .flatten()
+        .map(|&s| (s * 32768.) as i16)
+        .collect();
+    assert_eq!(samples, expected);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_record_float() {
+    let path = temp_path("record-float.wav");
+    let packets = packets(2);
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Float).unwrap();
+    let now = Instant::now();
+    for packet in &packets {
+        recorder.write(now, Some(TimingProfile::Pal), packet, false);
+    }
+    recorder.stop().unwrap();
+
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+    assert_eq!(reader.spec().bits_per_sample, 32);
+    let samples: Vec<f32> = reader.samples().collect::<Result<_, _>>().unwrap();
+    assert_eq!(bits(&samples), bits(&packets.concat()));
+    std::fs::remove_file(path).unwrap();
+}
+
+fn header_rate(profile: TimingProfile) -> u32 {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let rate = profile.sample_rate().round() as u32;
+    rate
+}
+
+#[test]
+fn test_header_waits_for_machine() {
+    // The samples before the machine is known end up in a file with its rate
+    let path = temp_path("record-rate.wav");
+    let packets = packets(2);
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    let now = Instant::now();
+    recorder.write(now, None, &packets[0], true);
+    recorder.write(now, Some(TimingProfile::Ntsc), &packets[1], false);
+    assert_eq!(recorder.stop(), Ok(vec![path.clone()]));
+
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().sample_rate, header_rate(TimingProfile::Ntsc));
+    assert_eq!(reader.duration(), 2 * 192);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_unknown_machine_is_recorded_as_pal() {
+    let path = temp_path("record-unknown.wav");
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    recorder.write(Instant::now(), None, &packets(1)[0], true);
+    recorder.stop().unwrap();
+
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().sample_rate, header_rate(TimingProfile::Pal));
+    assert_eq!(reader.duration(), 192);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_machine_change_splits_file() {
+    let path = temp_path("record-split.wav");
+    let second = temp_path("record-split-2.wav");
+    let packets = packets(3);
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    let now = Instant::now();
+    recorder.write(now, Some(TimingProfile::Pal), &packets[0], true);
+    recorder.write(now, Some(TimingProfile::Ntsc), &packets[1], false);
+    recorder.write(now, Some(TimingProfile::Ntsc), &packets[2], false);
+    assert_eq!(recorder.stop(), Ok(vec![path.clone(), second.clone()]));
+
+    let first = hound::WavReader::open(&path).unwrap();
+    assert_eq!(first.spec().sample_rate, header_rate(TimingProfile::Pal));
+    assert_eq!(first.duration(), 192);
+    let next = hound::WavReader::open(&second).unwrap();
+    assert_eq!(next.spec().sample_rate, header_rate(TimingProfile::Ntsc));
+    assert_eq!(next.duration(), 2 * 192);
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(second).unwrap();
+}
+
+#[test]
+fn test_header_updated_while_recording() {
+    // A recording that is never stopped is still readable up to the last second
+    let path = temp_path("record-unfinished.wav");
+    let packet = &packets(1)[0];
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    let now = Instant::now();
+    for _ in 0..300 {
+        recorder.write(now, Some(TimingProfile::Ntsc), packet, false);
+    }
+
+    let reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().sample_rate, header_rate(TimingProfile::Ntsc));
+    assert!(reader.duration() >= header_rate(TimingProfile::Ntsc));
+    recorder.stop().unwrap();
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_record_gap() {
+    // An outage is filled with silence as long as it lasted, up to the samples after it
+    let path = temp_path("record-gap.wav");
+    let packets = packets(2);
+    let profile = Some(TimingProfile::Pal);
+    let mut recorder = WavRecorder::new();
+    recorder.start(&path, WavFormat::Pcm16).unwrap();
+    let start = Instant::now();
+    recorder.write(start, profile, &packets[0], true);
+    recorder.write(
+        start + Duration::from_millis(100),
+        profile,
+        &packets[1],
+        true,
+    );
+    recorder.stop().unwrap();
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let gap = (0.1 * TimingProfile::Pal.sample_rate()) as usize;
+    let (_, samples) = read_i16(&path);
+    assert_eq!(samples.len(), 2 * (192 + gap));
+    let silence = &samples[2 * 192..2 * gap];
+    assert!(silence.iter().all(|&s| s == 0));
+    assert!(samples[2 * gap..].iter().any(|&s| s != 0));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_loopback_record_muted() {
+    let path = temp_path("record-muted.wav");
+    let wav = Arc::new(Mutex::new(WavRecorder::new()));
+    wav.lock().unwrap().start(&path, WavFormat::Pcm16).unwrap();
+    let harness = Harness::start_recording(wav.clone()).await.unwrap();
+    let mut tone = Tone::new(1000., 48_000.);
+    let packets: Vec<_> = (0..3).map(|_| tone.next_packet()).collect();
+    for (seq, samples) in (0..).zip(&packets) {
+        harness
+            .send_audio(&audio_packet(seq, samples))
+            .await
+            .unwrap();
+    }
+    harness.settle(0, 3).await;
+    harness.stop().await.unwrap();
+    wav.lock().unwrap().stop().unwrap();
+
+    let (_, samples) = read_i16(&path);
+    let expected: Vec<i16> = packets.iter().flatten().flatten().copied().collect();
+    assert_eq!(samples, expected);
+    std::fs::remove_file(path).unwrap();
+}